@@ -0,0 +1,256 @@
+//! Guest-side admission control for deadline-scheduled processes.
+//!
+//! An [`AdmissionController`] keeps the [`SchedulingParams`] of the live
+//! processes it spawned and runs a schedulability test before every new
+//! spawn, so an overloaded task set is caught at spawn time instead of as
+//! missed deadlines later. Periodic tasks go through
+//! [`AdmissionController::spawn_periodic`].
+//!
+//! The host does not tell the controller when a process finishes, call
+//! [`AdmissionController::release`] for that. A process that is never
+//! released keeps counting towards the utilization, and new spawns are
+//! rejected once the task set looks full.
+use std::{collections::HashMap, fmt};
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::{
+    config::SchedulingParams,
+    error::HperwasmError,
+    periodic::{self, PeriodicTask},
+    serializer::Serializer,
+    Mailbox, Process, ProcessConfig,
+};
+
+/// Scheduling policy the task set is tested against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Policy {
+    /// Earliest deadline first, tested with the density bound
+    /// `sum(wcet / deadline) <= 1`, exact for implicit deadlines.
+    Edf,
+    /// Fixed priorities by period, tested with the Liu & Layland bound and,
+    /// if that fails, exact response-time analysis.
+    RateMonotonic,
+}
+
+/// What happens to a spawn that fails the schedulability test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Enforcement {
+    Reject,
+    /// Spawn anyway and hand the [`Overload`] back to the caller.
+    Warn,
+}
+
+/// A process that would make the task set unschedulable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Overload {
+    pub policy: Policy,
+    /// Utilization of the process that failed the test.
+    pub added: f64,
+    /// Utilization of the task set including that process.
+    pub utilization: f64,
+}
+
+impl fmt::Display for Overload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a process with utilization {:.3} makes the task set unschedulable under {:?} (utilization {:.3})",
+            self.added, self.policy, self.utilization
+        )
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AdmissionError {
+    #[error("{0}")]
+    Unschedulable(Overload),
+    #[error("config has no scheduling parameters")]
+    MissingParams,
+    #[error("spawn failed: {0}")]
+    Spawn(#[from] HperwasmError),
+}
+
+/// Tracks live deadline processes and admits new ones only if the task set
+/// stays schedulable.
+#[derive(Debug, Clone)]
+pub struct AdmissionController {
+    policy: Policy,
+    enforcement: Enforcement,
+    tasks: HashMap<u64, SchedulingParams>,
+}
+
+impl AdmissionController {
+    pub fn new(policy: Policy) -> Self {
+        Self {
+            policy,
+            enforcement: Enforcement::Reject,
+            tasks: HashMap::new(),
+        }
+    }
+
+    /// Defaults to [`Enforcement::Reject`].
+    pub fn enforcement(mut self, enforcement: Enforcement) -> Self {
+        self.enforcement = enforcement;
+        self
+    }
+
+    /// Run the schedulability test for the tracked processes plus one with
+    /// `params`, regardless of the enforcement.
+    pub fn check(&self, params: &SchedulingParams) -> Result<(), Overload> {
+        let mut tasks: Vec<SchedulingParams> = self.tasks.values().copied().collect();
+        tasks.push(*params);
+        if is_schedulable(self.policy, &tasks) {
+            Ok(())
+        } else {
+            Err(Overload {
+                policy: self.policy,
+                added: params.utilization(),
+                utilization: utilization(&tasks),
+            })
+        }
+    }
+
+    /// Check whether a process with `params` may be added.
+    ///
+    /// With [`Enforcement::Warn`] an overload is returned as `Ok(Some(_))`.
+    pub fn admit(&self, params: &SchedulingParams) -> Result<Option<Overload>, AdmissionError> {
+        match (self.check(params), self.enforcement) {
+            (Ok(()), _) => Ok(None),
+            (Err(overload), Enforcement::Reject) => Err(AdmissionError::Unschedulable(overload)),
+            (Err(overload), Enforcement::Warn) => Ok(Some(overload)),
+        }
+    }
+
+    /// Spawn a process with `config` if its [`scheduling`](ProcessConfig::scheduling)
+    /// parameters pass the admission test, and track it until it is released.
+    ///
+    /// Also returns the overload a process admitted under
+    /// [`Enforcement::Warn`] causes.
+    ///
+    /// The controller doesn't notice when the process exits, links only
+    /// report failures. Call [`release`](Self::release) once it finished or
+    /// was killed, otherwise its utilization stays counted and later spawns
+    /// are rejected.
+    pub fn spawn<C, M, S>(
+        &mut self,
+        config: &ProcessConfig,
+        capture: C,
+        entry: fn(C, Mailbox<M, S>),
+    ) -> Result<(Process<M, S>, Option<Overload>), AdmissionError>
+    where
+        S: Serializer<C> + Serializer<M>,
+    {
+        let params = config.scheduling().ok_or(AdmissionError::MissingParams)?;
+        let overload = self.admit(&params)?;
+        let process = Process::<M, S>::spawn_config(config, capture, entry);
+        self.tasks.insert(process.id(), params);
+        Ok((process, overload))
+    }
+
+    /// Start a periodic task with [`periodic::spawn_periodic`] if `params`
+    /// pass the admission test, and track it until it is released with
+    /// [`release_periodic`](Self::release_periodic).
+    pub fn spawn_periodic<C>(
+        &mut self,
+        params: SchedulingParams,
+        capture: C,
+        body: fn(C),
+    ) -> Result<(PeriodicTask, Option<Overload>), AdmissionError>
+    where
+        C: Clone + Serialize + DeserializeOwned,
+    {
+        let overload = self.admit(&params)?;
+        let task = periodic::spawn_periodic(params, capture, body);
+        self.tasks.insert(task.id(), params);
+        Ok((task, overload))
+    }
+
+    /// Stop tracking a process, after it finished or was killed.
+    pub fn release(&mut self, process_id: u64) {
+        self.tasks.remove(&process_id);
+    }
+
+    /// Stop `task` and stop tracking it.
+    pub fn release_periodic(&mut self, task: PeriodicTask) {
+        self.release(task.id());
+    }
+
+    /// Total utilization of the tracked processes.
+    pub fn utilization(&self) -> f64 {
+        self.tasks.values().map(SchedulingParams::utilization).sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+fn utilization(tasks: &[SchedulingParams]) -> f64 {
+    tasks.iter().map(SchedulingParams::utilization).sum()
+}
+
+// Slack for rounding in the floating point utilization sums, so a task set
+// with a utilization of exactly 1 isn't rejected.
+const EPSILON: f64 = 1e-9;
+
+/// Schedulability of `tasks` on one processor under `policy`.
+pub fn is_schedulable(policy: Policy, tasks: &[SchedulingParams]) -> bool {
+    match policy {
+        Policy::Edf => tasks.iter().map(SchedulingParams::density).sum::<f64>() <= 1.0 + EPSILON,
+        Policy::RateMonotonic => liu_layland(tasks) || response_time_analysis(tasks),
+    }
+}
+
+/// Utilization bound of the Liu & Layland test for `n` tasks,
+/// `n * (2^(1/n) - 1)`.
+pub fn liu_layland_bound(n: usize) -> f64 {
+    if n == 0 {
+        return 1.0;
+    }
+    let n = n as f64;
+    n * (2f64.powf(1.0 / n) - 1.0)
+}
+
+/// Sufficient test for implicit deadlines.
+fn liu_layland(tasks: &[SchedulingParams]) -> bool {
+    if tasks.iter().any(|task| task.deadline() < task.period()) {
+        return false;
+    }
+    utilization(tasks) <= liu_layland_bound(tasks.len()) + EPSILON
+}
+
+/// Exact test for fixed priorities assigned by period, shortest first.
+///
+/// Works on nanoseconds, a response time that overflows counts as a miss.
+fn response_time_analysis(tasks: &[SchedulingParams]) -> bool {
+    let mut tasks = tasks.to_vec();
+    tasks.sort_by_key(SchedulingParams::period);
+    tasks.iter().enumerate().all(|(i, task)| {
+        let higher = &tasks[..i];
+        let (wcet, deadline) = (task.wcet().as_nanos(), task.deadline().as_nanos());
+        let mut response = wcet;
+        loop {
+            let interference = higher.iter().try_fold(0u128, |sum, other| {
+                let releases = response.div_ceil(other.period().as_nanos());
+                sum.checked_add(other.wcet().as_nanos().checked_mul(releases)?)
+            });
+            let Some(next) = interference.and_then(|interference| interference.checked_add(wcet))
+            else {
+                return false;
+            };
+            if next > deadline {
+                return false;
+            }
+            if next == response {
+                return true;
+            }
+            response = next;
+        }
+    })
+}
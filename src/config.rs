@@ -1,6 +1,15 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
 use crate::{error::HperwasmError, host};
 
-pub struct ProcessConfig(ProcessConfigType);
+/// Scheduling times are passed to the host in milliseconds.
+pub(crate) fn to_host_time(time: Duration) -> u64 {
+    time.as_millis() as u64
+}
+
+pub struct ProcessConfig(ProcessConfigType, Option<SchedulingParams>);
 
 enum ProcessConfigType {
     Config(u64),
@@ -9,16 +18,24 @@ enum ProcessConfigType {
 
 impl ProcessConfig {
     pub fn new() -> Result<Self, HperwasmError> {
+        crate::version::ensure_supported()?;
         match unsafe {
             host::api::process::create_config()
         } {
-            -1 => Err(HperwasmError::PermissionDenied),
-            id => Ok(Self(ProcessConfigType::Config(id as u64))),
+            -1 => Err(HperwasmError::PermissionDenied(
+                "the process is not allowed to create process configurations".to_string(),
+            )),
+            id => Ok(Self(ProcessConfigType::Config(id as u64), None)),
         }
     }
 
+    /// Create a configuration step by step, see [`ProcessConfigBuilder`].
+    pub fn builder() -> ProcessConfigBuilder {
+        ProcessConfigBuilder::default()
+    }
+
     pub(crate) fn inherit() -> Self {
-        Self(ProcessConfigType::Inherit)
+        Self(ProcessConfigType::Inherit, None)
     }
 
     pub fn id(&self) -> i64 {
@@ -34,14 +51,339 @@ impl ProcessConfig {
         }
     }
 
+    /// Set the expected time in milliseconds, clearing the parameters set
+    /// with [`set_scheduling`](Self::set_scheduling).
     pub fn set_expected_time(&mut self, time: u64) {
         unsafe {
             host::api::process::config_set_expected_time(self.id() as u64, time)
         }
+        self.1 = None;
     }
 
+    /// Set the relative deadline in milliseconds, clearing the parameters set
+    /// with [`set_scheduling`](Self::set_scheduling).
     pub fn set_relative_ddl(&mut self, time: u64) {
         unsafe {host::api::process::config_set_relative_ddl(self.id() as u64, time)}
+        self.1 = None;
+    }
+
+    /// Set the expected time and relative deadline from checked parameters.
+    pub fn set_scheduling(&mut self, params: SchedulingParams) {
+        self.set_expected_time(to_host_time(params.wcet()));
+        self.set_relative_ddl(to_host_time(params.deadline()));
+        self.1 = Some(params);
+    }
+
+    /// Parameters set with [`set_scheduling`](Self::set_scheduling).
+    pub fn scheduling(&self) -> Option<SchedulingParams> {
+        self.1
+    }
+
+    /// Set the maximum amount of memory in bytes a process can use.
+    pub fn set_max_memory(&mut self, max_memory: u64) {
+        unsafe { host::api::process::config_set_max_memory(self.id() as u64, max_memory) }
+    }
+
+    pub fn max_memory(&self) -> u64 {
+        unsafe { host::api::process::config_get_max_memory(self.id() as u64) }
+    }
+
+    /// Set the maximum amount of fuel a process can burn, 0 means unlimited.
+    pub fn set_max_fuel(&mut self, max_fuel: u64) {
+        unsafe { host::api::process::config_set_max_fuel(self.id() as u64, max_fuel) }
+    }
+
+    pub fn max_fuel(&self) -> u64 {
+        unsafe { host::api::process::config_get_max_fuel(self.id() as u64) }
+    }
+
+    /// Allow or forbid processes to compile new modules.
+    pub fn set_can_compile_modules(&mut self, can: bool) {
+        unsafe { host::api::process::config_set_can_compile_modules(self.id() as u64, can as u32) }
+    }
+
+    pub fn can_compile_modules(&self) -> bool {
+        unsafe { host::api::process::config_can_compile_modules(self.id() as u64) != 0 }
+    }
+
+    /// Allow or forbid processes to create their own configurations.
+    pub fn set_can_create_configs(&mut self, can: bool) {
+        unsafe { host::api::process::config_set_can_create_configs(self.id() as u64, can as u32) }
+    }
+
+    pub fn can_create_configs(&self) -> bool {
+        unsafe { host::api::process::config_can_create_configs(self.id() as u64) != 0 }
+    }
+
+    /// Allow or forbid processes to spawn other processes.
+    pub fn set_can_spawn_processes(&mut self, can: bool) {
+        unsafe { host::api::process::config_set_can_spawn_processes(self.id() as u64, can as u32) }
+    }
+
+    pub fn can_spawn_processes(&self) -> bool {
+        unsafe { host::api::process::config_can_spawn_processes(self.id() as u64) != 0 }
+    }
+
+    /// Add an environment variable, visible to the process through `std::env`.
+    pub fn env(&mut self, key: &str, value: &str) {
+        unsafe {
+            host::api::wasi::config_add_environment_variable(
+                self.id() as u64,
+                key.as_ptr(),
+                key.len(),
+                value.as_ptr(),
+                value.len(),
+            )
+        }
+    }
+
+    /// Add a command line argument, visible to the process through `std::env::args`.
+    pub fn arg(&mut self, arg: &str) {
+        unsafe {
+            host::api::wasi::config_add_command_line_argument(self.id() as u64, arg.as_ptr(), arg.len())
+        }
+    }
+
+    /// Add several command line arguments, in order.
+    pub fn args<I, A>(&mut self, args: I)
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<str>,
+    {
+        for arg in args {
+            self.arg(arg.as_ref());
+        }
+    }
+
+    /// Give the process access to the host directory `path` through `std::fs`.
+    pub fn preopen_dir(&mut self, path: &str) {
+        unsafe { host::api::wasi::config_preopen_dir(self.id() as u64, path.as_ptr(), path.len()) }
+    }
+
+}
+
+impl Drop for ProcessConfig {
+    fn drop(&mut self) {
+        if let ProcessConfigType::Config(id) = self.0 {
+            unsafe { host::api::process::drop_config(id) };
+        }
+    }
+}
+
+/// Timing of a deadline-scheduled process.
+///
+/// `wcet` is the worst-case execution time, `period` the minimal time between
+/// two releases and `deadline` is relative to each release.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SchedulingParams {
+    wcet: Duration,
+    period: Duration,
+    deadline: Duration,
+}
+
+impl SchedulingParams {
+    /// Fails unless `0 < wcet <= deadline <= period`.
+    pub fn new(
+        wcet: Duration,
+        period: Duration,
+        deadline: Duration,
+    ) -> Result<Self, InvalidSchedulingParams> {
+        if wcet.is_zero() {
+            Err(InvalidSchedulingParams::ZeroExecutionTime)
+        } else if wcet > deadline {
+            Err(InvalidSchedulingParams::ExecutionTimeExceedsDeadline { wcet, deadline })
+        } else if deadline > period {
+            Err(InvalidSchedulingParams::DeadlineExceedsPeriod { deadline, period })
+        } else {
+            Ok(Self {
+                wcet,
+                period,
+                deadline,
+            })
+        }
+    }
+
+    /// Parameters with the deadline equal to the period.
+    pub fn implicit(wcet: Duration, period: Duration) -> Result<Self, InvalidSchedulingParams> {
+        Self::new(wcet, period, period)
     }
 
-}
\ No newline at end of file
+    pub fn wcet(&self) -> Duration {
+        self.wcet
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    pub fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    /// Share of a processor the process needs, `wcet / period`.
+    pub fn utilization(&self) -> f64 {
+        self.wcet.as_secs_f64() / self.period.as_secs_f64()
+    }
+
+    /// `wcet / deadline`, equal to the utilization for implicit deadlines.
+    pub fn density(&self) -> f64 {
+        self.wcet.as_secs_f64() / self.deadline.as_secs_f64()
+    }
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidSchedulingParams {
+    #[error("execution time must be non-zero")]
+    ZeroExecutionTime,
+    #[error("execution time {wcet:?} exceeds the deadline {deadline:?}")]
+    ExecutionTimeExceedsDeadline { wcet: Duration, deadline: Duration },
+    #[error("deadline {deadline:?} exceeds the period {period:?}")]
+    DeadlineExceedsPeriod { deadline: Duration, period: Duration },
+}
+
+/// Builder for [`ProcessConfig`].
+///
+/// Only the settings that are called on the builder are sent to the host,
+/// everything else keeps the host defaults.
+///
+/// ```no_run
+/// let config = hyperwasm::ProcessConfig::builder()
+///     .name("worker")
+///     .max_memory(16 * 1024 * 1024)
+///     .deny_spawn()
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ProcessConfigBuilder {
+    name: Option<String>,
+    expected_time: Option<u64>,
+    relative_ddl: Option<u64>,
+    scheduling: Option<SchedulingParams>,
+    max_memory: Option<u64>,
+    max_fuel: Option<u64>,
+    can_compile_modules: Option<bool>,
+    can_create_configs: Option<bool>,
+    can_spawn_processes: Option<bool>,
+    envs: Vec<(String, String)>,
+    args: Vec<String>,
+    preopened_dirs: Vec<String>,
+}
+
+impl ProcessConfigBuilder {
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    pub fn expected_time(mut self, time: u64) -> Self {
+        self.expected_time = Some(time);
+        self
+    }
+
+    pub fn relative_ddl(mut self, time: u64) -> Self {
+        self.relative_ddl = Some(time);
+        self
+    }
+
+    /// Takes precedence over [`expected_time`](Self::expected_time) and
+    /// [`relative_ddl`](Self::relative_ddl).
+    pub fn scheduling(mut self, params: SchedulingParams) -> Self {
+        self.scheduling = Some(params);
+        self
+    }
+
+    pub fn max_memory(mut self, max_memory: u64) -> Self {
+        self.max_memory = Some(max_memory);
+        self
+    }
+
+    pub fn max_fuel(mut self, max_fuel: u64) -> Self {
+        self.max_fuel = Some(max_fuel);
+        self
+    }
+
+    pub fn can_compile_modules(mut self, can: bool) -> Self {
+        self.can_compile_modules = Some(can);
+        self
+    }
+
+    pub fn can_create_configs(mut self, can: bool) -> Self {
+        self.can_create_configs = Some(can);
+        self
+    }
+
+    pub fn can_spawn_processes(mut self, can: bool) -> Self {
+        self.can_spawn_processes = Some(can);
+        self
+    }
+
+    /// Forbid the process to spawn other processes.
+    pub fn deny_spawn(self) -> Self {
+        self.can_spawn_processes(false)
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.envs.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.args.push(arg.to_owned());
+        self
+    }
+
+    pub fn args<I, A>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<str>,
+    {
+        self.args.extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    pub fn preopen_dir(mut self, path: &str) -> Self {
+        self.preopened_dirs.push(path.to_owned());
+        self
+    }
+
+    /// Create the configuration on the host and apply all settings.
+    pub fn build(self) -> Result<ProcessConfig, HperwasmError> {
+        let mut config = ProcessConfig::new()?;
+        if let Some(name) = &self.name {
+            config.set_name(name);
+        }
+        if let Some(time) = self.expected_time {
+            config.set_expected_time(time);
+        }
+        if let Some(time) = self.relative_ddl {
+            config.set_relative_ddl(time);
+        }
+        if let Some(params) = self.scheduling {
+            config.set_scheduling(params);
+        }
+        if let Some(max_memory) = self.max_memory {
+            config.set_max_memory(max_memory);
+        }
+        if let Some(max_fuel) = self.max_fuel {
+            config.set_max_fuel(max_fuel);
+        }
+        if let Some(can) = self.can_compile_modules {
+            config.set_can_compile_modules(can);
+        }
+        if let Some(can) = self.can_create_configs {
+            config.set_can_create_configs(can);
+        }
+        if let Some(can) = self.can_spawn_processes {
+            config.set_can_spawn_processes(can);
+        }
+        for (key, value) in &self.envs {
+            config.env(key, value);
+        }
+        config.args(&self.args);
+        for path in &self.preopened_dirs {
+            config.preopen_dir(path);
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,27 @@
+//! Information about the nodes of a distributed hwe deployment.
+use crate::{host, version::Api};
+
+/// Ids of all nodes the current node is connected to.
+///
+/// Empty if the host has no distributed support.
+pub fn nodes() -> Vec<u64> {
+    if !Api::Distributed.is_available() {
+        return Vec::new();
+    }
+    let count = unsafe { host::api::distributed::nodes_count() };
+    let mut nodes = vec![0; count as usize];
+    let copied = unsafe { host::api::distributed::get_nodes(nodes.as_mut_ptr(), nodes.len() as u32) };
+    nodes.truncate(copied as usize);
+    nodes
+}
+
+/// Id of the node the current process runs on.
+pub fn node_id() -> u64 {
+    host::node_id()
+}
+
+/// Id of the module the current process was spawned from, as known to the
+/// other nodes.
+pub fn module_id() -> u64 {
+    unsafe { host::api::distributed::module_id() }
+}
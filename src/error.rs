@@ -1,62 +1,111 @@
-use std::fmt::{Debug, Display, Formatter};
+use std::io;
 
 use thiserror::Error;
 
-use crate::host::api::error;
+use crate::{
+    host::api::error,
+    version::{Api, Version},
+};
 
-
-#[derive(Error)]
+/// Errors reported by the hwe host.
+///
+/// Every variant keeps the message of the host. Host errors are sorted into
+/// one of the structured variants when the host reports an OS error code or
+/// a known error description, and into [`HperwasmError::Host`] otherwise.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum HperwasmError {
-    Error(u64),
-    PermissionDenied,
+    #[error("{0}")]
+    Host(String),
+    #[error("{0}")]
+    PermissionDenied(String),
+    #[error("{0}")]
+    Timeout(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    ConnectionRefused(String),
+    #[error("hwe host {host} is older than {required}, the oldest version supported by hyperwasm")]
+    UnsupportedHost { host: Version, required: Version },
+    #[error("hwe host {host} does not provide the {api:?} API, which needs {required}")]
+    ApiUnavailable {
+        api: Api,
+        host: Version,
+        required: Version,
+    },
 }
 
-impl Drop for HperwasmError {
-    fn drop(&mut self) {
-        match self {
-            HperwasmError::Error(id) => {
-                //unsafe { error::drop(*id) };
-                println!("error happen in {:?}", id);
-            }
-            HperwasmError::PermissionDenied => (),
+impl HperwasmError {
+    /// Take ownership of the host error `id`.
+    ///
+    /// The message is copied out and the host-side error is dropped right
+    /// away, so it is freed exactly once no matter how often the returned
+    /// value is cloned or formatted.
+    pub(crate) fn from(id: u64) -> Self {
+        let message = unsafe {
+            let size = error::string_size(id);
+            let mut buff = vec![0; size as usize];
+            error::to_string(id, buff.as_mut_ptr());
+            error::drop(id);
+            buff
+        };
+        Self::classify(String::from_utf8_lossy(&message).into_owned())
+    }
+
+    fn classify(message: String) -> Self {
+        let kind = match os_error_code(&message) {
+            Some(code) => match code {
+                1 | 13 => Some(io::ErrorKind::PermissionDenied),
+                2 => Some(io::ErrorKind::NotFound),
+                // Linux, BSD/macOS and Windows codes.
+                111 | 61 | 10061 => Some(io::ErrorKind::ConnectionRefused),
+                110 | 60 | 10060 => Some(io::ErrorKind::TimedOut),
+                _ => None,
+            },
+            None => description_kind(&message),
+        };
+        match kind {
+            Some(io::ErrorKind::PermissionDenied) => HperwasmError::PermissionDenied(message),
+            Some(io::ErrorKind::NotFound) => HperwasmError::NotFound(message),
+            Some(io::ErrorKind::ConnectionRefused) => HperwasmError::ConnectionRefused(message),
+            Some(io::ErrorKind::TimedOut) => HperwasmError::Timeout(message),
+            _ => HperwasmError::Host(message),
         }
     }
 }
 
-impl HperwasmError {
-    pub(crate) fn from(id: u64) -> Self {
-        HperwasmError::Error(id)
-    }
+/// The code of a message ending in `(os error N)`, the form of OS errors
+/// forwarded by the host.
+fn os_error_code(message: &str) -> Option<i32> {
+    let code = message.trim_end().strip_suffix(')')?;
+    let start = code.rfind("(os error ")?;
+    code[start + "(os error ".len()..].parse().ok()
 }
 
-impl Debug for HperwasmError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        match self {
-            HperwasmError::Error(_id) => {
-                // let size = unsafe { error::string_size(*id) };
-                // let mut buff = vec![0; size as usize];
-                // unsafe { error::to_string(*id, buff.as_mut_ptr()) };
-                // let error = std::str::from_utf8(&buff).unwrap();
-                let error = "test-error";
-                write!(f, "{}", error)
-            }
-            HperwasmError::PermissionDenied => write!(f, "Permission denied"),
-        }
+/// Match the last part of the message, after any `context: ` prefixes,
+/// against the descriptions std gives the error kinds.
+fn description_kind(message: &str) -> Option<io::ErrorKind> {
+    let description = message.rsplit(": ").next()?.trim().to_lowercase();
+    match description.as_str() {
+        "permission denied" => Some(io::ErrorKind::PermissionDenied),
+        "not found" | "entity not found" => Some(io::ErrorKind::NotFound),
+        "connection refused" => Some(io::ErrorKind::ConnectionRefused),
+        "timed out" | "operation timed out" | "connection timed out" => Some(io::ErrorKind::TimedOut),
+        _ => None,
     }
 }
 
-impl Display for HperwasmError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        match self {
-            HperwasmError::Error(_id) => {
-                // let size = unsafe { error::string_size(*id) };
-                // let mut buff = vec![0; size as usize];
-                // unsafe { error::to_string(*id, buff.as_mut_ptr()) };
-                // let error = std::str::from_utf8(&buff).unwrap();
-                let error = "test-error";
-                write!(f, "{}", error)
+impl From<HperwasmError> for io::Error {
+    fn from(err: HperwasmError) -> Self {
+        let kind = match err {
+            HperwasmError::Host(_) => io::ErrorKind::Other,
+            HperwasmError::PermissionDenied(_) => io::ErrorKind::PermissionDenied,
+            HperwasmError::Timeout(_) => io::ErrorKind::TimedOut,
+            HperwasmError::NotFound(_) => io::ErrorKind::NotFound,
+            HperwasmError::ConnectionRefused(_) => io::ErrorKind::ConnectionRefused,
+            HperwasmError::UnsupportedHost { .. } | HperwasmError::ApiUnavailable { .. } => {
+                io::ErrorKind::Unsupported
             }
-            HperwasmError::PermissionDenied => write!(f, "Permission denied"),
-        }
+        };
+        io::Error::new(kind, err)
     }
 }
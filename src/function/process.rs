@@ -1,7 +1,7 @@
 use std::{marker::PhantomData, time::Duration};
 use serde::{Deserialize, Serialize};
-use crate::{serializer::{Serializer, Bincode}, tag::Tag, ProcessConfig};
-use crate::host::{self,  process_id};
+use crate::{serializer::{write_message, Serializer, Bincode}, tag::Tag, timer::TimerRef, ProcessConfig};
+use crate::host::{self, node_id, process_id};
 
 pub trait IntoProcess<M, S> {
     type Process;
@@ -11,7 +11,7 @@ pub trait IntoProcess<M, S> {
         entry: fn(C, Self),
         link: Option<Tag>,
         config: Option<&ProcessConfig>,
-        //node: Option<u64>,
+        node: Option<u64>,
     ) -> Self::Process
     where
         S: Serializer<C> ;
@@ -21,23 +21,23 @@ pub trait NoLink {}
 
 #[derive(Serialize, Deserialize)]
 pub struct Process<M, S = Bincode> {
-    // node_id: u64,
+    node_id: u64,
     id: u64,
     #[serde(skip_serializing, default)]
     serializer_type: PhantomData<(M, S)>,
 }
 
 impl<M, S> Process<M, S> {
-    pub(crate) fn new(process_id: u64) -> Self {
+    pub(crate) fn new(node_id: u64, process_id: u64) -> Self {
         Self {
-            // node_id,
+            node_id,
             id: process_id,
             serializer_type: PhantomData,
         }
     }
 
     pub fn this() -> Self {
-        Self::new(process_id())
+        Self::new(node_id(), process_id())
     }
     /// Spawn a process.
     pub fn spawn<C, T>(capture: C, entry: fn(C, T)) -> T::Process
@@ -46,7 +46,7 @@ impl<M, S> Process<M, S> {
         T: IntoProcess<M, S>,
         T: NoLink,
     {
-        T::spawn(capture, entry, None, None)
+        T::spawn(capture, entry, None, None, None)
     }
 
 
@@ -57,19 +57,67 @@ impl<M, S> Process<M, S> {
         T: IntoProcess<M, S>,
         T: NoLink,
     {
-        T::spawn(capture, entry, None, Some(config))
+        T::spawn(capture, entry, None, Some(config), None)
+    }
+
+    /// Spawn a process on another node.
+    ///
+    /// Spawning on a node other than the current one needs the
+    /// `distributed` feature.
+    pub fn spawn_on<C, T>(node: u64, capture: C, entry: fn(C, T)) -> T::Process
+    where
+        S: Serializer<C> ,
+        T: IntoProcess<M, S>,
+        T: NoLink,
+    {
+        T::spawn(capture, entry, None, None, Some(node))
+    }
+
+    /// Spawn a process on another node with a custom configuration, see
+    /// [`Process::spawn_on`].
+    pub fn spawn_config_on<C, T>(
+        node: u64,
+        config: &ProcessConfig,
+        capture: C,
+        entry: fn(C, T),
+    ) -> T::Process
+    where
+        S: Serializer<C> ,
+        T: IntoProcess<M, S>,
+        T: NoLink,
+    {
+        T::spawn(capture, entry, None, Some(config), Some(node))
     }
 
 
     
 
 
-    pub fn spawn_link<C, T>(capture: C, entry: fn(C, T)) -> T::Process
+    /// Spawn a linked process.
+    ///
+    /// The returned tag is carried by [`MailboxResult::LinkDied`](crate::MailboxResult::LinkDied)
+    /// if the child dies, so it can be matched to this child.
+    pub fn spawn_link<C, T>(capture: C, entry: fn(C, T)) -> (T::Process, Tag)
+    where
+        S: Serializer<C> ,
+        T: IntoProcess<M, S>,
+    {
+        let tag = Tag::new();
+        (T::spawn(capture, entry, Some(tag), None, None), tag)
+    }
+
+    /// Spawn a linked process with a custom configuration.
+    pub fn spawn_link_config<C, T>(
+        config: &ProcessConfig,
+        capture: C,
+        entry: fn(C, T),
+    ) -> (T::Process, Tag)
     where
         S: Serializer<C> ,
         T: IntoProcess<M, S>,
     {
-        T::spawn(capture, entry, Some(Tag::new()), None)
+        let tag = Tag::new();
+        (T::spawn(capture, entry, Some(tag), Some(config), None), tag)
     }
 
 
@@ -80,34 +128,124 @@ impl<M, S> Process<M, S> {
         self.id
     }
 
+    /// The node the process is running on.
+    pub fn node_id(&self) -> u64 {
+        self.node_id
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.node_id == node_id()
+    }
+
 
 
 
    
-    pub fn link(&self) {
-        unsafe { host::api::process::link(0, self.id) };
+    /// Link the current process to this one.
+    ///
+    /// Returns the tag a [`MailboxResult::LinkDied`](crate::MailboxResult::LinkDied)
+    /// will carry if this process dies.
+    ///
+    /// Panics if the process runs on another node, links don't cross nodes.
+    #[track_caller]
+    pub fn link(&self) -> Tag {
+        let tag = Tag::new();
+        self.tag_link(tag);
+        tag
     }
 
-    
+    /// Link the current process to this one, reporting its death with `tag`.
+    ///
+    /// Panics if the process runs on another node.
+    #[track_caller]
+    pub fn tag_link(&self, tag: Tag) {
+        self.assert_local("link to");
+        unsafe { host::api::process::link(tag.id(), self.id) };
+    }
+
+    /// Panics if the process runs on another node.
+    #[track_caller]
     pub fn unlink(&self) {
+        self.assert_local("unlink");
         unsafe { host::api::process::unlink(self.id) };
     }
 
-    
+    /// Panics if the process runs on another node.
+    #[track_caller]
     pub fn kill(&self) {
+        self.assert_local("kill");
         unsafe { host::api::process::kill(self.id) };
     }
 
-    
+    /// Send `data` as is, bypassing the serializer.
+    ///
+    /// The receiver reads it with [`Mailbox::receive_raw`](crate::Mailbox::receive_raw).
+    pub fn send_raw(&self, data: &[u8]) {
+        self.send_raw_tagged(Tag::none(), data);
+    }
+
+    /// Send `data` as is, carrying `tag`.
+    pub fn send_raw_tagged(&self, tag: Tag, data: &[u8]) {
+        unsafe {
+            host::api::message::create_data(tag.id(), data.len() as u64);
+            host::api::message::write_data(data.as_ptr(), data.len());
+        }
+        host::send(self.node_id, self.id);
+    }
+
+    /// Register the process under `name` for this message and serializer type.
+    ///
+    /// The registry is per node, so this panics if the process runs on
+    /// another node.
+    #[track_caller]
     pub fn register(&self, name: &str) {
+        self.assert_local("register");
+        let name = Self::registry_name(name);
+        unsafe { host::api::registry::put(name.as_ptr(), name.len(), self.id) };
+    }
+
+    /// Look up a process registered under `name`.
+    ///
+    /// Returns `None` if nothing is registered under `name` with the same
+    /// message and serializer types.
+    pub fn lookup(name: &str) -> Option<Self> {
+        let name = Self::registry_name(name);
+        let mut id = 0;
+        let result = unsafe { host::api::registry::get(name.as_ptr(), name.len(), &mut id) };
+        if result == 0 {
+            Some(Self::new(node_id(), id))
+        } else {
+            None
+        }
+    }
+
+    /// Remove the registration of `name` for this message and serializer type.
+    pub fn unregister(name: &str) {
+        let name = Self::registry_name(name);
+        unsafe { host::api::registry::remove(name.as_ptr(), name.len()) };
+    }
+
+    // The host calls behind links, kills, the registry and timers only take
+    // a process id, they would act on the local process with the same id.
+    #[track_caller]
+    fn assert_local(&self, operation: &str) {
+        assert!(
+            self.is_local(),
+            "can't {} process {} on node {}, only processes on this node support it",
+            operation,
+            self.id,
+            self.node_id
+        );
+    }
+
+    fn registry_name(name: &str) -> String {
         // Encode type information in name
-        let name = format!(
+        format!(
             "{} + Process + {}/{}",
             name,
             std::any::type_name::<M>(),
             std::any::type_name::<S>()
-        );
-        unsafe { host::api::registry::put(name.as_ptr(), name.len(), self.id) };
+        )
     }
 
 
@@ -120,12 +258,42 @@ where
 {
 
     pub fn send(&self, message: M) {
+        self.tag_send(Tag::none(), message);
+    }
+
+    /// Send a message carrying `tag`, so the receiver can pick it with a tagged receive.
+    pub fn tag_send(&self, tag: Tag, message: M) {
+
+        write_message::<M, S>(tag, &message).unwrap();
 
-        unsafe { host::api::message::create_data(Tag::none().id(), 0) };
+        host::send(self.node_id, self.id);
+    }
 
-        S::encode(&message).unwrap();
+    /// Send a message to the process registered under `name`.
+    ///
+    /// Returns `false` if no process with this type is registered under `name`.
+    pub fn send_to_name(name: &str, message: M) -> bool {
+        match Self::lookup(name) {
+            Some(process) => {
+                process.send(message);
+                true
+            }
+            None => false,
+        }
+    }
 
-        host::send(self.id);
+    /// Send a message to the process after `duration`.
+    ///
+    /// The returned [`TimerRef`] can cancel the delivery. Panics if the
+    /// process runs on another node, timers only reach local processes.
+    #[track_caller]
+    pub fn send_after(&self, message: M, duration: Duration) -> TimerRef {
+        crate::version::assert_supported();
+        self.assert_local("send a timer message to");
+        write_message::<M, S>(Tag::none(), &message).unwrap();
+
+        let timer_id = unsafe { host::api::timer::send_after(self.id, duration.as_millis() as u64) };
+        TimerRef::from(timer_id)
     }
 
 
@@ -133,7 +301,7 @@ where
 
 impl<M, S> PartialEq for Process<M, S> {
     fn eq(&self, other: &Self) -> bool {
-        self.id() == other.id() 
+        self.node_id == other.node_id && self.id == other.id
     }
 }
 
@@ -141,7 +309,7 @@ impl<M, S> Eq for Process<M, S> {}
 
 impl<M, S> std::hash::Hash for Process<M, S> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        
+        self.node_id.hash(state);
         self.id.hash(state);
     }
 }
@@ -149,6 +317,7 @@ impl<M, S> std::hash::Hash for Process<M, S> {
 impl<M, S> std::fmt::Debug for Process<M, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Process")
+            .field("node_id", &self.node_id)
             .field("id", &self.id())
             .finish()
     }
@@ -157,6 +326,7 @@ impl<M, S> std::fmt::Debug for Process<M, S> {
 impl<M, S> Clone for Process<M, S> {
     fn clone(&self) -> Self {
         Self {
+            node_id: self.node_id,
             id: self.id,
             serializer_type: self.serializer_type,
         }
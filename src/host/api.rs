@@ -1,5 +1,14 @@
 //! hwe VM host functions.
+//!
+//! With the `mock_host` feature, the process, message, timer, registry,
+//! error, distributed and version functions are provided in-process instead.
 
+#[cfg(feature = "mock_host")]
+pub use super::mock::{error, message, process, registry, timer, version};
+#[cfg(all(feature = "mock_host", feature = "distributed"))]
+pub use super::mock::distributed;
+
+#[cfg(not(feature = "mock_host"))]
 pub mod error {
     #[link(wasm_import_module = "hwe::error")]
     extern "C" {
@@ -9,6 +18,7 @@ pub mod error {
     }
 }
 
+#[cfg(not(feature = "mock_host"))]
 pub mod message {
     #[link(wasm_import_module = "hwe::message")]
     extern "C" {
@@ -28,6 +38,7 @@ pub mod message {
     }
 }
 
+#[cfg(not(feature = "mock_host"))]
 pub mod timer {
     #[link(wasm_import_module = "hwe::timer")]
     extern "C" {
@@ -158,6 +169,7 @@ pub mod networking {
     }
 }
 
+#[cfg(not(feature = "mock_host"))]
 pub mod process {
     #[link(wasm_import_module = "hwe::process")]
     extern "C" {
@@ -197,6 +209,7 @@ pub mod process {
     }
 }
 
+#[cfg(not(feature = "mock_host"))]
 pub mod registry {
     #[link(wasm_import_module = "hwe::registry")]
     extern "C" {
@@ -226,6 +239,9 @@ pub mod wasi {
     }
 }
 
+// Only linked with the `distributed` feature, so guests for hosts without
+// hwe::distributed don't import it.
+#[cfg(all(feature = "distributed", not(feature = "mock_host")))]
 pub mod distributed {
     #[link(wasm_import_module = "hwe::distributed")]
     extern "C" {
@@ -248,6 +264,7 @@ pub mod distributed {
     }
 }
 
+#[cfg(not(feature = "mock_host"))]
 pub mod version {
     #[link(wasm_import_module = "hwe::version")]
     extern "C" {
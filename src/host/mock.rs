@@ -0,0 +1,658 @@
+//! In-process implementation of the hwe host functions, selected with the
+//! `mock_host` feature so code using processes can run under `cargo test`.
+//!
+//! Every process is an OS thread and every mailbox a queue searched by tag.
+//! The thread calling into the library first becomes a process on its own.
+//! Only functions of the running binary can be spawned, and there is a single
+//! node. Networking and WASI functions are not mocked.
+//!
+//! The functions keep the signatures of the wasm imports they replace, so
+//! callers have to uphold the same pointer contracts.
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::mailbox::{LINK_DIED, TIMEOUT};
+
+#[derive(Default)]
+struct Data {
+    tag: i64,
+    bytes: Vec<u8>,
+    cursor: usize,
+    resources: Vec<u64>,
+}
+
+enum Signal {
+    Message(Data),
+    LinkDied(i64),
+}
+
+impl Signal {
+    fn tag(&self) -> i64 {
+        match self {
+            Signal::Message(data) => data.tag,
+            Signal::LinkDied(tag) => *tag,
+        }
+    }
+}
+
+struct ProcessSlot {
+    mailbox: Mutex<VecDeque<Signal>>,
+    arrived: Condvar,
+    killed: AtomicBool,
+    die_when_link_dies: AtomicBool,
+    // Linked process ids and the tag their death is reported with.
+    links: Mutex<HashMap<u64, i64>>,
+}
+
+impl ProcessSlot {
+    fn new() -> Self {
+        Self {
+            mailbox: Mutex::new(VecDeque::new()),
+            arrived: Condvar::new(),
+            killed: AtomicBool::new(false),
+            die_when_link_dies: AtomicBool::new(true),
+            links: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn push(&self, signal: Signal) {
+        lock(&self.mailbox).push_back(signal);
+        self.arrived.notify_all();
+    }
+
+    fn kill(&self) {
+        self.killed.store(true, Ordering::SeqCst);
+        // Take the lock so a receiver can't miss the wake-up.
+        drop(lock(&self.mailbox));
+        self.arrived.notify_all();
+    }
+
+    fn check_killed(&self) {
+        if self.killed.load(Ordering::SeqCst) {
+            panic!("process was killed");
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+struct Config {
+    name: Option<String>,
+    max_memory: u64,
+    max_fuel: u64,
+    can_compile_modules: bool,
+    can_create_configs: bool,
+    can_spawn_processes: bool,
+    expected_time: u64,
+    relative_ddl: u64,
+}
+
+const TIMER_PENDING: u8 = 0;
+const TIMER_DONE: u8 = 1;
+
+#[derive(Default)]
+struct Runtime {
+    next_id: u64,
+    processes: HashMap<u64, Arc<ProcessSlot>>,
+    configs: HashMap<u64, Config>,
+    registry: HashMap<String, u64>,
+    errors: HashMap<u64, String>,
+    timers: HashMap<u64, Arc<AtomicU8>>,
+}
+
+impl Runtime {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn error(&mut self, message: &str) -> u64 {
+        let id = self.next_id();
+        self.errors.insert(id, message.to_owned());
+        id
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn runtime() -> MutexGuard<'static, Runtime> {
+    static RUNTIME: OnceLock<Mutex<Runtime>> = OnceLock::new();
+    lock(RUNTIME.get_or_init(Default::default))
+}
+
+struct Current {
+    id: u64,
+    slot: Arc<ProcessSlot>,
+    outgoing: Data,
+    incoming: Data,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Current>> = const { RefCell::new(None) };
+}
+
+fn with_current<R>(f: impl FnOnce(&mut Current) -> R) -> R {
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        let current = current.get_or_insert_with(|| {
+            let slot = Arc::new(ProcessSlot::new());
+            let mut runtime = runtime();
+            let id = runtime.next_id();
+            runtime.processes.insert(id, slot.clone());
+            Current {
+                id,
+                slot,
+                outgoing: Data::default(),
+                incoming: Data::default(),
+            }
+        });
+        f(current)
+    })
+}
+
+fn slot(process_id: u64) -> Option<Arc<ProcessSlot>> {
+    runtime().processes.get(&process_id).cloned()
+}
+
+fn deliver(process_id: u64, signal: Signal) {
+    if let Some(slot) = slot(process_id) {
+        slot.push(signal);
+    }
+}
+
+fn link_both(tag: i64, a: u64, b: u64) {
+    let (slot_a, slot_b) = (slot(a), slot(b));
+    if let (Some(slot_a), Some(slot_b)) = (slot_a, slot_b) {
+        lock(&slot_a.links).insert(b, tag);
+        lock(&slot_b.links).insert(a, tag);
+    }
+}
+
+/// Remove a finished process and notify the processes linked to it.
+fn exit(id: u64, failed: bool) {
+    let slot = {
+        let mut runtime = runtime();
+        runtime.registry.retain(|_, process_id| *process_id != id);
+        runtime.processes.remove(&id)
+    };
+    let Some(slot) = slot else { return };
+    let links = std::mem::take(&mut *lock(&slot.links));
+    for linked_id in links.into_keys() {
+        let Some(linked) = self::slot(linked_id) else { continue };
+        let tag = lock(&linked.links).remove(&id);
+        if let (true, Some(tag)) = (failed, tag) {
+            if linked.die_when_link_dies.load(Ordering::SeqCst) {
+                linked.kill();
+            } else {
+                linked.push(Signal::LinkDied(tag));
+            }
+        }
+    }
+}
+
+pub mod error {
+    use super::runtime;
+
+    pub unsafe fn string_size(error_id: u64) -> u32 {
+        runtime().errors.get(&error_id).map_or(0, |error| error.len() as u32)
+    }
+
+    pub unsafe fn to_string(error_id: u64, error_str: *mut u8) {
+        if let Some(error) = runtime().errors.get(&error_id) {
+            std::ptr::copy_nonoverlapping(error.as_ptr(), error_str, error.len());
+        }
+    }
+
+    pub unsafe fn drop(error_id: u64) {
+        runtime().errors.remove(&error_id);
+    }
+}
+
+pub mod message {
+    use std::slice;
+
+    use super::*;
+
+    pub unsafe fn create_data(tag: i64, capacity: u64) {
+        with_current(|current| {
+            current.outgoing = Data {
+                tag,
+                bytes: Vec::with_capacity(capacity as usize),
+                ..Data::default()
+            }
+        });
+    }
+
+    pub unsafe fn write_data(data: *const u8, data_len: usize) -> usize {
+        let data = slice::from_raw_parts(data, data_len);
+        with_current(|current| current.outgoing.bytes.extend_from_slice(data));
+        data_len
+    }
+
+    pub unsafe fn read_data(data: *mut u8, data_len: usize) -> usize {
+        let buf = slice::from_raw_parts_mut(data, data_len);
+        with_current(|current| {
+            let incoming = &mut current.incoming;
+            let rest = &incoming.bytes[incoming.cursor.min(incoming.bytes.len())..];
+            let len = rest.len().min(buf.len());
+            buf[..len].copy_from_slice(&rest[..len]);
+            incoming.cursor += len;
+            len
+        })
+    }
+
+    pub unsafe fn seek_data(position: u64) {
+        with_current(|current| current.incoming.cursor = position as usize);
+    }
+
+    pub unsafe fn get_tag() -> i64 {
+        with_current(|current| current.incoming.tag)
+    }
+
+    pub unsafe fn data_size() -> u64 {
+        with_current(|current| current.incoming.bytes.len() as u64)
+    }
+
+    pub unsafe fn push_tcp_stream(tcp_stream_id: u64) -> u64 {
+        with_current(|current| {
+            current.outgoing.resources.push(tcp_stream_id);
+            current.outgoing.resources.len() as u64 - 1
+        })
+    }
+
+    pub unsafe fn take_tcp_stream(index: u64) -> u64 {
+        with_current(|current| current.incoming.resources[index as usize])
+    }
+
+    pub unsafe fn send(process_id: u64) {
+        let data = with_current(|current| std::mem::take(&mut current.outgoing));
+        deliver(process_id, Signal::Message(data));
+    }
+
+    pub unsafe fn send_receive_skip_search(process_id: u64, timeout: u64) -> u32 {
+        let tag = with_current(|current| current.outgoing.tag);
+        send(process_id);
+        receive(&tag, 1, timeout)
+    }
+
+    pub unsafe fn receive(tag: *const i64, tag_len: usize, timeout: u64) -> u32 {
+        let tags = slice::from_raw_parts(tag, tag_len);
+        let slot = with_current(|current| current.slot.clone());
+        let deadline = match timeout {
+            u64::MAX => None,
+            ms => Some(Instant::now() + Duration::from_millis(ms)),
+        };
+
+        let mut mailbox = lock(&slot.mailbox);
+        let signal = loop {
+            if slot.killed.load(Ordering::SeqCst) {
+                drop(mailbox);
+                panic!("process was killed");
+            }
+            let position = mailbox
+                .iter()
+                .position(|signal| tags.is_empty() || tags.contains(&signal.tag()));
+            if let Some(signal) = position.and_then(|position| mailbox.remove(position)) {
+                break signal;
+            }
+            mailbox = match deadline {
+                None => slot.arrived.wait(mailbox).unwrap_or_else(|e| e.into_inner()),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return TIMEOUT;
+                    }
+                    slot.arrived
+                        .wait_timeout(mailbox, deadline - now)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+            };
+        };
+        drop(mailbox);
+
+        with_current(|current| match signal {
+            Signal::Message(data) => {
+                current.incoming = data;
+                0
+            }
+            Signal::LinkDied(tag) => {
+                current.incoming = Data {
+                    tag,
+                    ..Data::default()
+                };
+                LINK_DIED
+            }
+        })
+    }
+}
+
+pub mod timer {
+    use super::*;
+
+    pub unsafe fn send_after(process_id: u64, duration: u64) -> u64 {
+        let data = with_current(|current| std::mem::take(&mut current.outgoing));
+        let state = Arc::new(AtomicU8::new(TIMER_PENDING));
+        let id = {
+            let mut runtime = runtime();
+            let id = runtime.next_id();
+            runtime.timers.insert(id, state.clone());
+            id
+        };
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(duration));
+            let fire = state
+                .compare_exchange(TIMER_PENDING, TIMER_DONE, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok();
+            runtime().timers.remove(&id);
+            if fire {
+                deliver(process_id, Signal::Message(data));
+            }
+        });
+        id
+    }
+
+    pub unsafe fn cancel_timer(timer_id: u64) -> u32 {
+        let state = runtime().timers.remove(&timer_id);
+        state.is_some_and(|state| {
+            state
+                .compare_exchange(TIMER_PENDING, TIMER_DONE, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        }) as u32
+    }
+}
+
+pub mod process {
+    use std::slice;
+
+    use super::*;
+
+    pub unsafe fn compile_module(_data: *const u8, _data_len: usize, id: *mut u64) -> i32 {
+        *id = runtime().error("the mock host can't compile modules");
+        1
+    }
+
+    pub unsafe fn drop_module(_config_id: u64) {}
+
+    pub unsafe fn create_config() -> i64 {
+        let mut runtime = runtime();
+        let id = runtime.next_id();
+        runtime.configs.insert(
+            id,
+            Config {
+                can_spawn_processes: true,
+                can_create_configs: true,
+                can_compile_modules: true,
+                ..Config::default()
+            },
+        );
+        id as i64
+    }
+
+    pub unsafe fn drop_config(config_id: u64) {
+        runtime().configs.remove(&config_id);
+    }
+
+    fn update(config_id: u64, f: impl FnOnce(&mut Config)) {
+        if let Some(config) = runtime().configs.get_mut(&config_id) {
+            f(config);
+        }
+    }
+
+    fn get<T>(config_id: u64, f: impl FnOnce(&Config) -> T) -> T {
+        let runtime = runtime();
+        let config = runtime.configs.get(&config_id).cloned().unwrap_or_default();
+        f(&config)
+    }
+
+    pub unsafe fn config_set_max_memory(config_id: u64, max_memory: u64) {
+        update(config_id, |config| config.max_memory = max_memory);
+    }
+
+    pub unsafe fn config_get_max_memory(config_id: u64) -> u64 {
+        get(config_id, |config| config.max_memory)
+    }
+
+    pub unsafe fn config_set_max_fuel(config_id: u64, max_fuel: u64) {
+        update(config_id, |config| config.max_fuel = max_fuel);
+    }
+
+    pub unsafe fn config_get_max_fuel(config_id: u64) -> u64 {
+        get(config_id, |config| config.max_fuel)
+    }
+
+    pub unsafe fn config_can_compile_modules(config_id: u64) -> u32 {
+        get(config_id, |config| config.can_compile_modules as u32)
+    }
+
+    pub unsafe fn config_set_can_compile_modules(config_id: u64, can: u32) {
+        update(config_id, |config| config.can_compile_modules = can != 0);
+    }
+
+    pub unsafe fn config_can_create_configs(config_id: u64) -> u32 {
+        get(config_id, |config| config.can_create_configs as u32)
+    }
+
+    pub unsafe fn config_set_can_create_configs(config_id: u64, can: u32) {
+        update(config_id, |config| config.can_create_configs = can != 0);
+    }
+
+    pub unsafe fn config_can_spawn_processes(config_id: u64) -> u32 {
+        get(config_id, |config| config.can_spawn_processes as u32)
+    }
+
+    pub unsafe fn config_set_can_spawn_processes(config_id: u64, can: u32) {
+        update(config_id, |config| config.can_spawn_processes = can != 0);
+    }
+
+    pub unsafe fn config_set_name(config_id: u64, name: *const u8, name_len: usize) {
+        let name = String::from_utf8_lossy(slice::from_raw_parts(name, name_len)).into_owned();
+        update(config_id, |config| config.name = Some(name));
+    }
+
+    pub unsafe fn config_set_expected_time(config_id: u64, time: u64) {
+        update(config_id, |config| config.expected_time = time);
+    }
+
+    pub unsafe fn config_set_relative_ddl(config_id: u64, time: u64) {
+        update(config_id, |config| config.relative_ddl = time);
+    }
+
+    /// Only `_lunatic_spawn_by_index` of the current module can be spawned.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn spawn(
+        link: i64,
+        config_id: i64,
+        module_id: i64,
+        function: *const u8,
+        function_len: usize,
+        params: *const u8,
+        params_len: usize,
+        id: *mut u64,
+    ) -> u32 {
+        let function = slice::from_raw_parts(function, function_len);
+        let params = slice::from_raw_parts(params, params_len);
+        if module_id != -1 || function != b"_lunatic_spawn_by_index" || params.len() != 34 {
+            *id = runtime().error("the mock host can only spawn functions of the current module");
+            return 1;
+        }
+        let param = |index: usize| {
+            let start = index * 17 + 1;
+            u128::from_le_bytes(params[start..start + 16].try_into().unwrap()) as usize
+        };
+        let (entry, arg) = (param(0), param(1));
+
+        let parent = with_current(|current| current.id);
+        let slot = Arc::new(ProcessSlot::new());
+        let (child, name) = {
+            let mut runtime = runtime();
+            let config = runtime.configs.get(&(config_id as u64)).cloned();
+            let child = runtime.next_id();
+            runtime.processes.insert(child, slot.clone());
+            let name = config.and_then(|config| config.name);
+            (child, name.unwrap_or_else(|| format!("process {}", child)))
+        };
+        if link != 0 {
+            link_both(link, parent, child);
+        }
+
+        let spawned = thread::Builder::new().name(name).spawn(move || {
+            CURRENT.with(|current| {
+                *current.borrow_mut() = Some(Current {
+                    id: child,
+                    slot,
+                    outgoing: Data::default(),
+                    incoming: Data::default(),
+                })
+            });
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let function: fn(usize) = std::mem::transmute(entry);
+                function(arg);
+            }));
+            exit(child, result.is_err());
+        });
+        match spawned {
+            Ok(_) => {
+                *id = child;
+                0
+            }
+            Err(err) => {
+                exit(child, false);
+                *id = runtime().error(&err.to_string());
+                1
+            }
+        }
+    }
+
+    pub unsafe fn sleep_ms(millis: u64) {
+        thread::sleep(Duration::from_millis(millis));
+        with_current(|current| current.slot.check_killed());
+    }
+
+    pub unsafe fn die_when_link_dies(trap: u32) {
+        with_current(|current| current.slot.die_when_link_dies.store(trap != 0, Ordering::SeqCst));
+    }
+
+    pub unsafe fn process_id() -> u64 {
+        with_current(|current| current.id)
+    }
+
+    pub unsafe fn link(tag: i64, process_id: u64) {
+        let current = with_current(|current| current.id);
+        link_both(tag, current, process_id);
+    }
+
+    pub unsafe fn unlink(process_id: u64) {
+        let current = with_current(|current| current.slot.clone());
+        lock(&current.links).remove(&process_id);
+        if let Some(other) = slot(process_id) {
+            lock(&other.links).remove(&self::process_id());
+        }
+    }
+
+    pub unsafe fn kill(process_id: u64) {
+        if let Some(slot) = slot(process_id) {
+            slot.kill();
+        }
+    }
+}
+
+pub mod registry {
+    use std::slice;
+
+    use super::runtime;
+
+    unsafe fn name(name: *const u8, name_len: usize) -> String {
+        String::from_utf8_lossy(slice::from_raw_parts(name, name_len)).into_owned()
+    }
+
+    pub unsafe fn put(name: *const u8, name_len: usize, process_id: u64) {
+        runtime().registry.insert(self::name(name, name_len), process_id);
+    }
+
+    pub unsafe fn get(name: *const u8, name_len: usize, process_id: *mut u64) -> u32 {
+        match runtime().registry.get(&self::name(name, name_len)) {
+            Some(id) => {
+                *process_id = *id;
+                0
+            }
+            None => 1,
+        }
+    }
+
+    pub unsafe fn remove(name: *const u8, name_len: usize) {
+        runtime().registry.remove(&self::name(name, name_len));
+    }
+}
+
+#[cfg(feature = "distributed")]
+pub mod distributed {
+    use super::runtime;
+
+    pub unsafe fn get_nodes(_nodes_ptr: *mut u64, _nodes_len: u32) -> u32 {
+        0
+    }
+
+    pub unsafe fn nodes_count() -> u32 {
+        0
+    }
+
+    pub unsafe fn node_id() -> u64 {
+        0
+    }
+
+    pub unsafe fn module_id() -> u64 {
+        0
+    }
+
+    pub unsafe fn send(_node_id: u64, process_id: u64) {
+        super::message::send(process_id)
+    }
+
+    pub unsafe fn send_receive_skip_search(_node_id: u64, process_id: u64, timeout: u64) -> u32 {
+        super::message::send_receive_skip_search(process_id, timeout)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn spawn(
+        _node_id: u64,
+        _config_id: i64,
+        _module_id: u64,
+        _function: *const u8,
+        _function_len: usize,
+        _params: *const u8,
+        _params_len: usize,
+        id: *mut u64,
+    ) -> u32 {
+        *id = runtime().error("the mock host has no other nodes");
+        1
+    }
+}
+
+pub mod version {
+    use crate::version::LATEST_HOST_VERSION;
+
+    pub unsafe fn major() -> u32 {
+        LATEST_HOST_VERSION.major
+    }
+
+    pub unsafe fn minor() -> u32 {
+        LATEST_HOST_VERSION.minor
+    }
+
+    pub unsafe fn patch() -> u32 {
+        LATEST_HOST_VERSION.patch
+    }
+}
@@ -1,45 +1,118 @@
-use crate::{tag::Tag, error::HperwasmError, module::{params_to_vec, Param}, ProcessConfig};
+use crate::{tag::Tag, error::HperwasmError, module::{params_to_vec, Param}, version, ProcessConfig};
 
 pub mod api;
+#[cfg(feature = "mock_host")]
+mod mock;
 
 pub(crate) fn spawn(
-    //node: Option<u64>,
+    node: Option<u64>,
     config: Option<&ProcessConfig>,
     link: Option<Tag>,
     entry: fn(usize),
     arg: usize,
 ) -> Result<u64, HperwasmError> {
+    version::ensure_supported()?;
     let entry = entry as usize ;
     
-    let params = params_to_vec(&[Param::I32(entry as i32), Param::I32(arg as i32)]);
-    let mut id = 0;
+    let params = params_to_vec(&[usize_param(entry), usize_param(arg)]);
     let func = "_lunatic_spawn_by_index";
+    match node {
+        Some(node) if node != node_id() => spawn_remote(node, config, func, &params),
+        _ => spawn_function(config, link, -1, func, &params),
+    }
+}
+
+/// Pointers are 32 bit inside wasm, but the native mock host needs all 64.
+fn usize_param(value: usize) -> Param {
+    if usize::BITS == 32 {
+        Param::I32(value as i32)
+    } else {
+        Param::I64(value as i64)
+    }
+}
+
+/// Spawn a process running the exported `func` of the current module on
+/// another node. Remote processes can't be linked.
+#[cfg(not(feature = "distributed"))]
+fn spawn_remote(
+    _node: u64,
+    _config: Option<&ProcessConfig>,
+    _func: &str,
+    _params: &[u8],
+) -> Result<u64, HperwasmError> {
+    Err(HperwasmError::Host(
+        "spawning on another node needs the `distributed` feature".to_string(),
+    ))
+}
+
+/// Spawn a process running the exported `func` of the current module on
+/// another node. Remote processes can't be linked.
+#[cfg(feature = "distributed")]
+fn spawn_remote(
+    node: u64,
+    config: Option<&ProcessConfig>,
+    func: &str,
+    params: &[u8],
+) -> Result<u64, HperwasmError> {
+    use version::Api;
+    if !Api::Distributed.is_available() {
+        return Err(HperwasmError::ApiUnavailable {
+            api: Api::Distributed,
+            host: version::host_version(),
+            required: Api::Distributed.min_version(),
+        });
+    }
+    let mut id = 0;
+    let config_id = config.map_or_else(|| ProcessConfig::inherit().id(), |config| config.id());
+    let result = unsafe {
+        api::distributed::spawn(
+            node,
+            config_id,
+            api::distributed::module_id(),
+            func.as_ptr(),
+            func.len(),
+            params.as_ptr(),
+            params.len(),
+            &mut id,
+        )
+    };
+    if result == 0 {
+        Ok(id)
+    } else {
+        Err(HperwasmError::from(id))
+    }
+}
+
+/// Spawn a process running the exported `func` of module `module_id`, -1
+/// being the module of the current process.
+pub(crate) fn spawn_function(
+    config: Option<&ProcessConfig>,
+    link: Option<Tag>,
+    module_id: i64,
+    func: &str,
+    params: &[u8],
+) -> Result<u64, HperwasmError> {
+    let mut id = 0;
     let link = match link {
         Some(tag) => tag.id(),
         None => 0,
     };
     let config_id = config.map_or_else(|| ProcessConfig::inherit().id(), |config| config.id());
-    
     let result = unsafe {
-            println!("happen spawn {:?}", config_id);
-            api::process::spawn(
-                link,
-                config_id,
-                -1,
-                func.as_ptr(),
-                func.len(),
-                params.as_ptr(),
-                params.len(),
-                &mut id,
-            )
-        };
-        
-
+        api::process::spawn(
+            link,
+            config_id,
+            module_id,
+            func.as_ptr(),
+            func.len(),
+            params.as_ptr(),
+            params.len(),
+            &mut id,
+        )
+    };
     if result == 0 {
-        println!("spawn finish {:?}", config_id);
         Ok(id)
     } else {
-        
         Err(HperwasmError::from(id))
     }
 }
@@ -48,14 +121,45 @@ pub fn process_id() -> u64 {
     unsafe { api::process::process_id() }
 }
 
-// pub fn node_id() -> u64 {
-//     unsafe { api::distributed::node_id() }
-// }
-
-pub fn send(process_id: u64) {
+/// Id of the current node, 0 without distributed support.
+///
+/// The id is queried once, sends to local processes don't call into
+/// hwe::distributed.
+pub fn node_id() -> u64 {
+    #[cfg(feature = "distributed")]
+    {
+        static NODE_ID: std::sync::OnceLock<u64> = std::sync::OnceLock::new();
+        *NODE_ID.get_or_init(|| {
+            if version::Api::Distributed.is_available() {
+                unsafe { api::distributed::node_id() }
+            } else {
+                0
+            }
+        })
+    }
+    #[cfg(not(feature = "distributed"))]
+    0
+}
 
-        unsafe { api::message::send(process_id) }
+#[cfg_attr(not(feature = "distributed"), allow(unused_variables))]
+pub fn send(node_id: u64, process_id: u64) {
+    version::assert_supported();
+    #[cfg(feature = "distributed")]
+    if node_id != self::node_id() {
+        return unsafe { api::distributed::send(node_id, process_id) };
+    }
+    unsafe { api::message::send(process_id) }
+}
 
+/// Send the current message and wait for a reply carrying the same tag.
+#[cfg_attr(not(feature = "distributed"), allow(unused_variables))]
+pub fn send_receive_skip_search(node_id: u64, process_id: u64, timeout: u64) -> u32 {
+    version::assert_supported();
+    #[cfg(feature = "distributed")]
+    if node_id != self::node_id() {
+        return unsafe { api::distributed::send_receive_skip_search(node_id, process_id, timeout) };
+    }
+    unsafe { api::message::send_receive_skip_search(process_id, timeout) }
 }
 
 #[export_name = "_lunatic_spawn_by_index"]
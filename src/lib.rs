@@ -2,13 +2,33 @@ mod macros;
 mod tag;
 mod mailbox;
 mod error;
-mod module;
 mod config;
 
 pub mod host;
 pub mod function;
 pub mod serializer;
+pub mod module;
+pub mod net;
+#[cfg(feature = "distributed")]
+#[cfg_attr(docsrs, doc(cfg(feature = "distributed")))]
+pub mod distributed;
+pub mod timer;
+pub mod request;
+pub mod protocol;
+pub mod supervisor;
+pub mod server;
+pub mod periodic;
+pub mod admission;
+pub mod stream;
+pub mod version;
 
 pub use function::process::Process;
 pub use mailbox::{Mailbox, MailboxResult};
-pub use config::ProcessConfig;
\ No newline at end of file
+pub use module::Module;
+pub use periodic::spawn_periodic;
+pub use request::{Request, RequestError};
+pub use config::{InvalidSchedulingParams, ProcessConfig, ProcessConfigBuilder, SchedulingParams};
+pub use error::HperwasmError;
+pub use tag::Tag;
+pub use timer::sleep;
+pub use version::host_version;
\ No newline at end of file
@@ -1,9 +1,9 @@
 use std::{marker::PhantomData, time::Duration, fmt};
 
-use crate::{serializer::{Bincode, Serializer, DecodeError}, function::process::{Process, NoLink, IntoProcess}, host::{self, api::message}, tag::Tag, ProcessConfig};
+use crate::{serializer::{Bincode, Serializer, DecodeError}, function::process::{Process, NoLink, IntoProcess}, host::{self, api::message}, tag::Tag, version, ProcessConfig};
 
-const LINK_DIED: u32 = 1;
-const TIMEOUT: u32 = 9027;
+pub(crate) const LINK_DIED: u32 = 1;
+pub(crate) const TIMEOUT: u32 = 9027;
 
 pub struct Catching;
 
@@ -31,6 +31,17 @@ where
         self.receive_(tags, None).unwrap()
     }
 
+    /// Receive the next message as raw bytes, without decoding it.
+    #[track_caller]
+    pub fn receive_raw(&self) -> Vec<u8> {
+        self.receive_raw_(&[], None).unwrap()
+    }
+
+    #[track_caller]
+    pub fn tag_receive_raw(&self, tags: &[Tag]) -> Vec<u8> {
+        self.receive_raw_(tags, None).unwrap()
+    }
+
 
     pub fn catch_link_failure(self) -> Mailbox<M, S, Catching> {
         unsafe {
@@ -46,7 +57,7 @@ where
 {
 
     pub fn this(&self) -> Process<M, S> {
-        Process::new(host::process_id())
+        Process::new(host::node_id(), host::process_id())
     }
 
    
@@ -64,21 +75,16 @@ where
         self.receive_(tags, Some(timeout))
     }
 
+    pub fn receive_raw_timeout(&self, timeout: Duration) -> MailboxResult<Vec<u8>> {
+        self.receive_raw_(&[], Some(timeout))
+    }
+
     fn receive_(&self, tags: &[Tag], timeout: Option<Duration>) -> MailboxResult<M> {
-        let tags: Vec<i64> = tags.iter().map(|tag| tag.id()).collect();
-        let timeout_ms = match timeout {
-            Some(timeout) => timeout.as_millis() as u64,
-            None => u64::MAX,
-        };
-        let message_type = unsafe { message::receive(tags.as_ptr(), tags.len(), timeout_ms) };
-        match message_type {
-            LINK_DIED => MailboxResult::LinkDied(unsafe { Tag::from(message::get_tag()) }),
-            TIMEOUT => MailboxResult::TimedOut,
-            _ => match S::decode() {
-                Ok(msg) => MailboxResult::Message(msg),
-                Err(err) => MailboxResult::DeserializationFailed(err),
-            },
-        }
+        receive_with(tags, timeout, S::decode)
+    }
+
+    fn receive_raw_(&self, tags: &[Tag], timeout: Option<Duration>) -> MailboxResult<Vec<u8>> {
+        receive_with(tags, timeout, read_raw)
     }
 
 
@@ -89,6 +95,37 @@ where
     }
 }
 
+/// Read the whole current message as bytes, sized by the host.
+pub(crate) fn read_raw() -> Result<Vec<u8>, DecodeError> {
+    let size = unsafe { message::data_size() } as usize;
+    let mut data = vec![0; size];
+    let read = unsafe { message::read_data(data.as_mut_ptr(), size) };
+    data.truncate(read);
+    Ok(data)
+}
+
+pub(crate) fn receive_with<T>(
+    tags: &[Tag],
+    timeout: Option<Duration>,
+    read: impl FnOnce() -> Result<T, DecodeError>,
+) -> MailboxResult<T> {
+    version::assert_supported();
+    let tags: Vec<i64> = tags.iter().map(|tag| tag.id()).collect();
+    let timeout_ms = match timeout {
+        Some(timeout) => timeout.as_millis() as u64,
+        None => u64::MAX,
+    };
+    let message_type = unsafe { message::receive(tags.as_ptr(), tags.len(), timeout_ms) };
+    match message_type {
+        LINK_DIED => MailboxResult::LinkDied(unsafe { Tag::from(message::get_tag()) }),
+        TIMEOUT => MailboxResult::TimedOut,
+        _ => match read() {
+            Ok(msg) => MailboxResult::Message(msg),
+            Err(err) => MailboxResult::DeserializationFailed(err),
+        },
+    }
+}
+
 impl<M, S> Mailbox<M, S, Catching>
 where
     S: Serializer<M>,
@@ -103,6 +140,10 @@ where
     pub fn tag_receive(&self, tags: &[Tag]) -> MailboxResult<M> {
         self.receive_(tags, None)
     }
+
+    pub fn receive_raw(&self) -> MailboxResult<Vec<u8>> {
+        self.receive_raw_(&[], None)
+    }
 }
 
 impl<M, S, L> Clone for Mailbox<M, S, L>
@@ -180,29 +221,24 @@ where
         entry: fn(C, Self),
         link: Option<Tag>,
         config: Option<&ProcessConfig>,
-        //node: Option<u64>,
+        node: Option<u64>,
     ) -> Self::Process
     where
         S: Serializer<C> + Serializer<M>,
     {
         let entry = entry as usize ;
-        //let node_id = node.unwrap_or_else(host::node_id);
+        let node_id = node.unwrap_or_else(host::node_id);
 
   
-        match host::spawn( config, link, type_helper_wrapper::<C, M, S>, entry) {
+        match host::spawn(Some(node_id), config, link, type_helper_wrapper::<C, M, S>, entry) {
             Ok(id) => {
                 // If the captured variable is of size 0, we don't need to send it to another
                 // process.
-                // if std::mem::size_of::<C>() == 0 {
-                    
-                    Process::new( id)
-                    
-                // } else {
-                //     let child = Process::<C, S>::new(id);
-                //     child.send(capture);
-
-                //     unsafe { std::mem::transmute(child) }
-                // }
+                if std::mem::size_of::<C>() != 0 {
+                    let child = Process::<C, S>::new(node_id, id);
+                    child.send(capture);
+                }
+                Process::new(node_id, id)
             }
             Err(err) => panic!("Failed to spawn a process: {}", err),
         }
@@ -214,12 +250,13 @@ fn type_helper_wrapper<C, M, S>(function: usize)
 where
     S: Serializer<C> + Serializer<M>,
 {
-    
-    let captured = //if std::mem::size_of::<C>() == 0 {
-        unsafe { std::mem::MaybeUninit::<C>::zeroed().assume_init() };
-    // } else {
-    //     unsafe { Mailbox::<C, S>::new() }.receive()
-    // };
+    // The parent sends a non zero-sized capture as the first message, before
+    // anyone else can know about this process.
+    let captured = if std::mem::size_of::<C>() == 0 {
+        unsafe { std::mem::MaybeUninit::<C>::zeroed().assume_init() }
+    } else {
+        unsafe { Mailbox::<C, S>::new() }.receive()
+    };
     let mailbox = unsafe { Mailbox::new() };
     let function: fn(C, Mailbox<M, S>) = unsafe { std::mem::transmute(function ) };
     function(captured, mailbox);
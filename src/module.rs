@@ -1,3 +1,58 @@
+//! Compiled WebAssembly modules and the parameters passed to their functions.
+use crate::{error::HperwasmError, host, Process, ProcessConfig};
+
+/// A WebAssembly module compiled by the host.
+///
+/// Processes can be spawned from any function the module exports. The module
+/// is released when the value is dropped, already running processes are not
+/// affected.
+#[derive(Debug)]
+pub struct Module {
+    id: u64,
+}
+
+impl Module {
+    /// Compile the WebAssembly binary `data`.
+    pub fn new(data: &[u8]) -> Result<Self, HperwasmError> {
+        crate::version::ensure_supported()?;
+        let mut id = 0;
+        match unsafe { host::api::process::compile_module(data.as_ptr(), data.len(), &mut id) } {
+            0 => Ok(Self { id }),
+            -1 => Err(HperwasmError::PermissionDenied(
+                "the process is not allowed to compile modules".to_string(),
+            )),
+            _ => Err(HperwasmError::from(id)),
+        }
+    }
+
+    /// Spawn a process running the exported function `function` with `params`.
+    ///
+    /// Without a `config` the process inherits the configuration of the
+    /// current process.
+    pub fn spawn<M, S>(
+        &self,
+        config: Option<&ProcessConfig>,
+        function: &str,
+        params: &[Param],
+    ) -> Result<Process<M, S>, HperwasmError> {
+        let params = params_to_vec(params);
+        let id = host::spawn_function(config, None, self.id as i64, function, &params)?;
+        Ok(Process::new(host::node_id(), id))
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        unsafe { host::api::process::drop_module(self.id) };
+    }
+}
+
+/// A typed argument of an exported function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Param {
     I32(i32),
     I64(i64),
@@ -0,0 +1,296 @@
+//! Networking primitives backed by the hwe::networking host functions.
+mod tcp_listener;
+mod tcp_stream;
+mod udp;
+
+use std::{
+    io, iter, option, slice, vec,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    time::Duration,
+};
+
+pub use tcp_listener::TcpListener;
+pub use tcp_stream::TcpStream;
+pub use udp::UdpSocket;
+
+use crate::{error::HperwasmError, host::api::networking, mailbox::TIMEOUT, version};
+
+/// Iterator over the socket addresses held by a host DNS iterator.
+///
+/// The host hands these out for resolved names, local addresses and peers.
+#[derive(Debug)]
+pub struct SocketAddrIterator {
+    id: u64,
+}
+
+impl SocketAddrIterator {
+    pub(crate) fn from(id: u64) -> Self {
+        Self { id }
+    }
+}
+
+impl Iterator for SocketAddrIterator {
+    type Item = SocketAddr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut addr_type = 0;
+        let mut addr = [0u8; 16];
+        let mut port = 0;
+        let mut flow_info = 0;
+        let mut scope_id = 0;
+        let next = unsafe {
+            networking::resolve_next(
+                self.id,
+                &mut addr_type,
+                addr.as_mut_ptr(),
+                &mut port,
+                &mut flow_info,
+                &mut scope_id,
+            )
+        };
+        if next != 0 {
+            return None;
+        }
+        match addr_type {
+            4 => {
+                let ip = Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]);
+                Some(SocketAddrV4::new(ip, port).into())
+            }
+            6 => {
+                let ip = Ipv6Addr::from(addr);
+                Some(SocketAddrV6::new(ip, port, flow_info, scope_id).into())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Drop for SocketAddrIterator {
+    fn drop(&mut self) {
+        unsafe { networking::drop_dns_iterator(self.id) };
+    }
+}
+
+/// Resolves a `"host:port"` name into socket addresses.
+///
+/// `None` waits for the host resolver without a timeout.
+pub fn resolve(name: &str, timeout: Option<Duration>) -> io::Result<SocketAddrIterator> {
+    version::ensure_supported()?;
+    let mut dns_iter_or_error_id = 0;
+    let result = unsafe {
+        networking::resolve(
+            name.as_ptr(),
+            name.len(),
+            timeout_to_ms(timeout),
+            &mut dns_iter_or_error_id,
+        )
+    };
+    match result {
+        0 => Ok(SocketAddrIterator::from(dns_iter_or_error_id)),
+        TIMEOUT => Err(timed_out("resolve")),
+        _ => Err(host_error(dns_iter_or_error_id)),
+    }
+}
+
+/// A value that can be turned into one or more socket addresses.
+///
+/// This mirrors [`std::net::ToSocketAddrs`], but resolves names through the
+/// host instead of the system resolver.
+pub trait ToSocketAddrs {
+    type Iter: Iterator<Item = SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter>;
+}
+
+impl ToSocketAddrs for SocketAddr {
+    type Iter = option::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(Some(*self).into_iter())
+    }
+}
+
+impl ToSocketAddrs for SocketAddrV4 {
+    type Iter = option::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        SocketAddr::V4(*self).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for SocketAddrV6 {
+    type Iter = option::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        SocketAddr::V6(*self).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for (IpAddr, u16) {
+    type Iter = option::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        SocketAddr::new(self.0, self.1).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for (Ipv4Addr, u16) {
+    type Iter = option::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        SocketAddrV4::new(self.0, self.1).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for (Ipv6Addr, u16) {
+    type Iter = option::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        SocketAddrV6::new(self.0, self.1, 0, 0).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for (&str, u16) {
+    type Iter = vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        let (host, port) = *self;
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)].into_iter());
+        }
+        let addrs: Vec<_> = resolve(&format!("{}:{}", host, port), None)?.collect();
+        Ok(addrs.into_iter())
+    }
+}
+
+impl ToSocketAddrs for (String, u16) {
+    type Iter = vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        (self.0.as_str(), self.1).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for str {
+    type Iter = vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        if let Ok(addr) = self.parse::<SocketAddr>() {
+            return Ok(vec![addr].into_iter());
+        }
+        let addrs: Vec<_> = resolve(self, None)?.collect();
+        Ok(addrs.into_iter())
+    }
+}
+
+impl ToSocketAddrs for String {
+    type Iter = vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        self.as_str().to_socket_addrs()
+    }
+}
+
+impl<'a> ToSocketAddrs for &'a [SocketAddr] {
+    type Iter = iter::Cloned<slice::Iter<'a, SocketAddr>>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(self.iter().cloned())
+    }
+}
+
+impl<T: ToSocketAddrs + ?Sized> ToSocketAddrs for &T {
+    type Iter = T::Iter;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        (**self).to_socket_addrs()
+    }
+}
+
+/// Calls `f` with each address in turn until one succeeds, returning the
+/// last error otherwise. This is how std's constructors treat multiple addresses.
+pub(crate) fn each_addr<A, F, T>(addr: A, mut f: F) -> io::Result<T>
+where
+    A: ToSocketAddrs,
+    F: FnMut(SocketAddr) -> io::Result<T>,
+{
+    version::ensure_supported()?;
+    let mut last_err = None;
+    for addr in addr.to_socket_addrs()? {
+        match f(addr) {
+            Ok(value) => return Ok(value),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "could not resolve to any addresses")
+    }))
+}
+
+/// A socket address laid out the way the host functions expect it.
+pub(crate) struct HostSocketAddr {
+    pub(crate) addr_type: u32,
+    pub(crate) addr: [u8; 16],
+    pub(crate) port: u32,
+    pub(crate) flow_info: u32,
+    pub(crate) scope_id: u32,
+}
+
+impl From<&SocketAddr> for HostSocketAddr {
+    fn from(addr: &SocketAddr) -> Self {
+        let mut octets = [0u8; 16];
+        match addr {
+            SocketAddr::V4(v4) => {
+                octets[..4].copy_from_slice(&v4.ip().octets());
+                Self {
+                    addr_type: 4,
+                    addr: octets,
+                    port: v4.port() as u32,
+                    flow_info: 0,
+                    scope_id: 0,
+                }
+            }
+            SocketAddr::V6(v6) => {
+                octets.copy_from_slice(&v6.ip().octets());
+                Self {
+                    addr_type: 6,
+                    addr: octets,
+                    port: v6.port() as u32,
+                    flow_info: v6.flowinfo(),
+                    scope_id: v6.scope_id(),
+                }
+            }
+        }
+    }
+}
+
+/// Wraps a host error id into an [`io::Error`] of the matching kind.
+pub(crate) fn host_error(error_id: u64) -> io::Error {
+    HperwasmError::from(error_id).into()
+}
+
+pub(crate) fn timed_out(operation: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, format!("{} timed out", operation))
+}
+
+/// Host timeouts are in milliseconds, `u64::MAX` meaning no timeout.
+pub(crate) fn timeout_to_ms(timeout: Option<Duration>) -> u64 {
+    timeout.map_or(u64::MAX, |timeout| timeout.as_millis() as u64)
+}
+
+pub(crate) fn ms_to_timeout(ms: u64) -> Option<Duration> {
+    match ms {
+        u64::MAX => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// Decodes the address behind a DNS iterator id, or the host error if `result` is non-zero.
+pub(crate) fn first_addr(result: u32, id: u64) -> io::Result<SocketAddr> {
+    if result != 0 {
+        return Err(host_error(id));
+    }
+    SocketAddrIterator::from(id)
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host returned no address"))
+}
@@ -0,0 +1,71 @@
+use std::{io, net::SocketAddr};
+
+use super::{each_addr, first_addr, host_error, HostSocketAddr, TcpStream, ToSocketAddrs};
+use crate::host::api::networking;
+
+/// A TCP server, listening for connections.
+///
+/// The listener is closed when the value is dropped.
+#[derive(Debug)]
+pub struct TcpListener {
+    id: u64,
+}
+
+impl TcpListener {
+    /// Creates a new listener bound to `addr`.
+    ///
+    /// If `addr` yields several addresses, each is tried until one succeeds.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        each_addr(addr, Self::bind_)
+    }
+
+    fn bind_(addr: SocketAddr) -> io::Result<Self> {
+        let addr = HostSocketAddr::from(&addr);
+        let mut id = 0;
+        let result = unsafe {
+            networking::tcp_bind(
+                addr.addr_type,
+                addr.addr.as_ptr(),
+                addr.port,
+                addr.flow_info,
+                addr.scope_id,
+                &mut id,
+            )
+        };
+        if result == 0 {
+            Ok(Self { id })
+        } else {
+            Err(host_error(id))
+        }
+    }
+
+    /// Blocks until a new connection is established and returns it together
+    /// with the address of the peer.
+    pub fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        let mut id = 0;
+        let mut peer_dns_iter = 0;
+        let result = unsafe { networking::tcp_accept(self.id, &mut id, &mut peer_dns_iter) };
+        if result != 0 {
+            return Err(host_error(id));
+        }
+        let stream = TcpStream::from(id);
+        Ok((stream, first_addr(0, peer_dns_iter)?))
+    }
+
+    /// Returns the local address this listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        let mut dns_iter_or_error_id = 0;
+        let result = unsafe { networking::tcp_local_addr(self.id, &mut dns_iter_or_error_id) };
+        first_addr(result, dns_iter_or_error_id)
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for TcpListener {
+    fn drop(&mut self) {
+        unsafe { networking::drop_tcp_listener(self.id) };
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    io::{self, IoSlice, Read, Write},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use super::{
+    each_addr, host_error, ms_to_timeout, timed_out, timeout_to_ms, HostSocketAddr,
+    ToSocketAddrs, TIMEOUT,
+};
+use serde::{ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    host::api::{message, networking},
+    serializer::move_tcp_stream,
+};
+
+/// A TCP connection.
+///
+/// Cloning the stream asks the host for a second handle to the same
+/// connection. The handle is released when the value is dropped.
+///
+/// A stream can be part of a message. Serializing it moves the connection
+/// into the message being built, so the sending process loses access to it,
+/// and deserializing takes it out of the received message. Serializing it
+/// outside of a message send fails, and so does any operation on a stream
+/// that was moved.
+#[derive(Debug)]
+pub struct TcpStream {
+    id: u64,
+    // Set once the host resource has been moved into a message.
+    consumed: Arc<AtomicBool>,
+}
+
+impl TcpStream {
+    pub(crate) fn from(id: u64) -> Self {
+        Self {
+            id,
+            consumed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Opens a connection to `addr`.
+    ///
+    /// If `addr` yields several addresses, each is tried until one succeeds.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        each_addr(addr, |addr| Self::connect_(addr, None))
+    }
+
+    /// Opens a connection to `addr`, giving up after `timeout`.
+    pub fn connect_timeout(addr: SocketAddr, timeout: Duration) -> io::Result<Self> {
+        Self::connect_(addr, Some(timeout))
+    }
+
+    fn connect_(addr: SocketAddr, timeout: Option<Duration>) -> io::Result<Self> {
+        let addr = HostSocketAddr::from(&addr);
+        let mut id = 0;
+        let result = unsafe {
+            networking::tcp_connect(
+                addr.addr_type,
+                addr.addr.as_ptr(),
+                addr.port,
+                addr.flow_info,
+                addr.scope_id,
+                timeout_to_ms(timeout),
+                &mut id,
+            )
+        };
+        match result {
+            0 => Ok(Self::from(id)),
+            TIMEOUT => Err(timed_out("TcpStream connect")),
+            _ => Err(host_error(id)),
+        }
+    }
+
+    /// Reads into `buf` without removing the data from the queue.
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut nread_or_error_id = 0;
+        let result = unsafe {
+            networking::tcp_peek(self.live_id()?, buf.as_mut_ptr(), buf.len(), &mut nread_or_error_id)
+        };
+        match result {
+            0 => Ok(nread_or_error_id as usize),
+            TIMEOUT => Err(timed_out("TcpStream peek")),
+            _ => Err(host_error(nread_or_error_id)),
+        }
+    }
+
+    /// Sets the read timeout, `None` blocks indefinitely.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let result = unsafe { networking::set_read_timeout(self.live_id()?, timeout_to_ms(timeout)) };
+        timeout_result(result)
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        let id = self.live_id().ok()?;
+        ms_to_timeout(unsafe { networking::get_read_timeout(id) })
+    }
+
+    /// Sets the write timeout, `None` blocks indefinitely.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let result = unsafe { networking::set_write_timeout(self.live_id()?, timeout_to_ms(timeout)) };
+        timeout_result(result)
+    }
+
+    pub fn write_timeout(&self) -> Option<Duration> {
+        let id = self.live_id().ok()?;
+        ms_to_timeout(unsafe { networking::get_write_timeout(id) })
+    }
+
+    /// Sets the peek timeout, `None` blocks indefinitely.
+    pub fn set_peek_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let result = unsafe { networking::set_peek_timeout(self.live_id()?, timeout_to_ms(timeout)) };
+        timeout_result(result)
+    }
+
+    pub fn peek_timeout(&self) -> Option<Duration> {
+        let id = self.live_id().ok()?;
+        ms_to_timeout(unsafe { networking::get_peek_timeout(id) })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Asks the host for a second handle to the same connection.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self::from(unsafe { networking::clone_tcp_stream(self.live_id()?) }))
+    }
+
+    fn live_id(&self) -> io::Result<u64> {
+        if self.consumed.load(Ordering::Relaxed) {
+            Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "TcpStream was moved into a message",
+            ))
+        } else {
+            Ok(self.id)
+        }
+    }
+}
+
+fn timeout_result(result: u32) -> io::Result<()> {
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid timeout"))
+    }
+}
+
+impl Read for TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self).read(buf)
+    }
+}
+
+impl Read for &TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut nread_or_error_id = 0;
+        let result = unsafe {
+            networking::tcp_read(self.live_id()?, buf.as_mut_ptr(), buf.len(), &mut nread_or_error_id)
+        };
+        match result {
+            0 => Ok(nread_or_error_id as usize),
+            TIMEOUT => Err(timed_out("TcpStream read")),
+            _ => Err(host_error(nread_or_error_id)),
+        }
+    }
+}
+
+impl Write for TcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self).write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        (&*self).write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self).flush()
+    }
+}
+
+impl Write for &TcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_vectored(&[IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let mut nwritten_or_error_id = 0;
+        // On wasm32 an `IoSlice` has the same layout as a WASI ciovec, a
+        // (pointer, length) pair of u32 values.
+        let result = unsafe {
+            networking::tcp_write_vectored(
+                self.live_id()?,
+                bufs.as_ptr() as *const u32,
+                bufs.len(),
+                &mut nwritten_or_error_id,
+            )
+        };
+        match result {
+            0 => Ok(nwritten_or_error_id as usize),
+            TIMEOUT => Err(timed_out("TcpStream write")),
+            _ => Err(host_error(nwritten_or_error_id)),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut error_id = 0;
+        match unsafe { networking::tcp_flush(self.live_id()?, &mut error_id) } {
+            0 => Ok(()),
+            _ => Err(host_error(error_id)),
+        }
+    }
+}
+
+impl Clone for TcpStream {
+    #[track_caller]
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap()
+    }
+}
+
+impl Drop for TcpStream {
+    fn drop(&mut self) {
+        if !self.consumed.load(Ordering::Relaxed) {
+            unsafe { networking::drop_tcp_stream(self.id) };
+        }
+    }
+}
+
+impl Serialize for TcpStream {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.consumed.load(Ordering::Relaxed) {
+            return Err(S::Error::custom("TcpStream was already moved into a message"));
+        }
+        let index = move_tcp_stream(self.id, &self.consumed).map_err(S::Error::custom)?;
+        serializer.serialize_u64(index)
+    }
+}
+
+impl<'de> Deserialize<'de> for TcpStream {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let index = u64::deserialize(deserializer)?;
+        let id = unsafe { message::take_tcp_stream(index) };
+        Ok(TcpStream::from(id))
+    }
+}
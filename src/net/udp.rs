@@ -0,0 +1,193 @@
+use std::{io, net::SocketAddr, time::Duration};
+
+use super::{
+    each_addr, first_addr, host_error, timed_out, timeout_to_ms, HostSocketAddr, ToSocketAddrs,
+    TIMEOUT,
+};
+use crate::host::api::networking;
+
+/// A UDP socket.
+///
+/// Cloning the socket asks the host for a second handle to it. The handle is
+/// released when the value is dropped.
+#[derive(Debug)]
+pub struct UdpSocket {
+    id: u64,
+}
+
+impl UdpSocket {
+    /// Creates a UDP socket bound to `addr`.
+    ///
+    /// If `addr` yields several addresses, each is tried until one succeeds.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        each_addr(addr, Self::bind_)
+    }
+
+    fn bind_(addr: SocketAddr) -> io::Result<Self> {
+        let addr = HostSocketAddr::from(&addr);
+        let mut id = 0;
+        let result = unsafe {
+            networking::udp_bind(
+                addr.addr_type,
+                addr.addr.as_ptr(),
+                addr.port,
+                addr.flow_info,
+                addr.scope_id,
+                &mut id,
+            )
+        };
+        if result == 0 {
+            Ok(Self { id })
+        } else {
+            Err(host_error(id))
+        }
+    }
+
+    /// Connects the socket to a remote address, so that [`send`](Self::send)
+    /// and [`recv`](Self::recv) can be used.
+    pub fn connect<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        each_addr(addr, |addr| self.connect_(addr, None))
+    }
+
+    /// Same as [`connect`](Self::connect), but gives up after `timeout`.
+    pub fn connect_timeout(&self, addr: SocketAddr, timeout: Duration) -> io::Result<()> {
+        self.connect_(addr, Some(timeout))
+    }
+
+    fn connect_(&self, addr: SocketAddr, timeout: Option<Duration>) -> io::Result<()> {
+        let addr = HostSocketAddr::from(&addr);
+        let mut error_id = 0;
+        let result = unsafe {
+            networking::udp_connect(
+                self.id,
+                addr.addr_type,
+                addr.addr.as_ptr(),
+                addr.port,
+                addr.flow_info,
+                addr.scope_id,
+                timeout_to_ms(timeout),
+                &mut error_id,
+            )
+        };
+        match result {
+            0 => Ok(()),
+            TIMEOUT => Err(timed_out("UdpSocket connect")),
+            _ => Err(host_error(error_id)),
+        }
+    }
+
+    /// Sends `buf` to the connected remote address.
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut nsent_or_error_id = 0;
+        let result = unsafe {
+            networking::udp_send(self.id, buf.as_ptr(), buf.len(), &mut nsent_or_error_id)
+        };
+        match result {
+            0 => Ok(nsent_or_error_id as usize),
+            _ => Err(host_error(nsent_or_error_id)),
+        }
+    }
+
+    /// Sends `buf` to `addr`, using the first address it resolves to.
+    pub fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
+        let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no addresses to send data to")
+        })?;
+        let addr = HostSocketAddr::from(&addr);
+        let mut nsent_or_error_id = 0;
+        let result = unsafe {
+            networking::udp_send_to(
+                self.id,
+                buf.as_ptr(),
+                buf.len(),
+                addr.addr_type,
+                addr.addr.as_ptr(),
+                addr.port,
+                addr.flow_info,
+                addr.scope_id,
+                &mut nsent_or_error_id,
+            )
+        };
+        match result {
+            0 => Ok(nsent_or_error_id as usize),
+            _ => Err(host_error(nsent_or_error_id)),
+        }
+    }
+
+    /// Receives a datagram from the connected remote address.
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut nread_or_error_id = 0;
+        let result = unsafe {
+            networking::udp_receive(self.id, buf.as_mut_ptr(), buf.len(), &mut nread_or_error_id)
+        };
+        match result {
+            0 => Ok(nread_or_error_id as usize),
+            TIMEOUT => Err(timed_out("UdpSocket receive")),
+            _ => Err(host_error(nread_or_error_id)),
+        }
+    }
+
+    /// Receives a datagram and returns the number of bytes read together with
+    /// the address it came from.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let mut nread_or_error_id = 0;
+        let mut dns_iter_id = 0;
+        let result = unsafe {
+            networking::udp_receive_from(
+                self.id,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut nread_or_error_id,
+                &mut dns_iter_id,
+            )
+        };
+        match result {
+            0 => Ok((nread_or_error_id as usize, first_addr(0, dns_iter_id)?)),
+            TIMEOUT => Err(timed_out("UdpSocket receive")),
+            _ => Err(host_error(nread_or_error_id)),
+        }
+    }
+
+    /// Returns the local address this socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        let mut dns_iter_or_error_id = 0;
+        let result = unsafe { networking::udp_local_addr(self.id, &mut dns_iter_or_error_id) };
+        first_addr(result, dns_iter_or_error_id)
+    }
+
+    /// Sets the `IP_TTL` option on this socket.
+    pub fn set_ttl(&self, ttl: u32) {
+        unsafe { networking::set_udp_socket_ttl(self.id, ttl) }
+    }
+
+    pub fn ttl(&self) -> u32 {
+        unsafe { networking::get_udp_socket_ttl(self.id) }
+    }
+
+    /// Sets the `SO_BROADCAST` option on this socket.
+    pub fn set_broadcast(&self, broadcast: bool) {
+        unsafe { networking::set_udp_socket_broadcast(self.id, broadcast as u32) }
+    }
+
+    pub fn broadcast(&self) -> bool {
+        unsafe { networking::get_udp_socket_broadcast(self.id) != 0 }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Clone for UdpSocket {
+    fn clone(&self) -> Self {
+        Self {
+            id: unsafe { networking::clone_udp_socket(self.id) },
+        }
+    }
+}
+
+impl Drop for UdpSocket {
+    fn drop(&mut self) {
+        unsafe { networking::drop_udp_socket(self.id) };
+    }
+}
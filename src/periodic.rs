@@ -0,0 +1,205 @@
+//! Periodic real-time tasks.
+//!
+//! [`spawn_periodic`] starts a release process that spawns one job per period.
+//! Every job gets a config with the task's worst-case execution time as
+//! expected time and its relative deadline, so the deadline-aware scheduler
+//! can order it. Jobs that finish after their deadline, or haven't finished
+//! when it passes, are reported back to the spawning process.
+//!
+//! The release process is linked to the spawning process and stops when the
+//! [`PeriodicTask`] handle is dropped.
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{config::SchedulingParams, tag::Tag, Mailbox, MailboxResult, Process, ProcessConfig};
+
+/// A job of a periodic task that did not meet its deadline.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobReport {
+    /// Job `job` finished, but only `response_time` after its release.
+    Late { job: u64, response_time: Duration },
+    /// Job `job` was still running when its deadline passed.
+    Missed { job: u64 },
+}
+
+impl JobReport {
+    pub fn job(&self) -> u64 {
+        match self {
+            JobReport::Late { job, .. } | JobReport::Missed { job } => *job,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum ReleaserMessage {
+    Done(u64),
+    Stop,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Task<C> {
+    capture: C,
+    body: usize,
+    params: SchedulingParams,
+    spawner: Process<JobReport>,
+    tag: Tag,
+}
+
+/// Handle to a task started with [`spawn_periodic`].
+///
+/// Dropping the handle stops the task.
+#[derive(Debug)]
+pub struct PeriodicTask {
+    releaser: Process<ReleaserMessage>,
+    tag: Tag,
+    link: Tag,
+    stopped: Cell<bool>,
+}
+
+impl PeriodicTask {
+    /// Wait up to `timeout` for the next report of a late or missed job.
+    ///
+    /// Reports are sent to the process that spawned the task, so only that
+    /// process can receive them.
+    pub fn next_report(&self, timeout: Duration) -> Option<JobReport> {
+        let mailbox = unsafe { Mailbox::<JobReport>::new() };
+        match mailbox.tag_receive_timeout(&[self.tag], timeout) {
+            MailboxResult::Message(report) => Some(report),
+            _ => None,
+        }
+    }
+
+    /// Stop releasing new jobs. Jobs already running are not interrupted.
+    pub fn stop(&self) {
+        if !self.stopped.replace(true) {
+            self.releaser.send(ReleaserMessage::Stop);
+        }
+    }
+
+    /// Id of the release process, which an
+    /// [`AdmissionController`](crate::admission::AdmissionController) tracks
+    /// the task under.
+    pub fn id(&self) -> u64 {
+        self.releaser.id()
+    }
+
+    /// Tag of the link to the release process, carried by
+    /// [`MailboxResult::LinkDied`] if it fails.
+    pub fn link_tag(&self) -> Tag {
+        self.link
+    }
+}
+
+impl Drop for PeriodicTask {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Run `body` with a copy of `capture` every period of `params`.
+///
+/// The wcet of `params` is the worst-case execution time of one job and its
+/// deadline is relative to each release.
+///
+/// No admission test is run, use
+/// [`AdmissionController::spawn_periodic`](crate::admission::AdmissionController::spawn_periodic)
+/// to keep the task set schedulable.
+pub fn spawn_periodic<C>(params: SchedulingParams, capture: C, body: fn(C)) -> PeriodicTask
+where
+    C: Clone + Serialize + DeserializeOwned,
+{
+    let tag = Tag::new();
+    let task = Task {
+        capture,
+        body: body as usize,
+        params,
+        spawner: Process::this(),
+        tag,
+    };
+    let (releaser, link) = Process::spawn_link(task, releaser_entry::<C>);
+    PeriodicTask {
+        releaser,
+        tag,
+        link,
+        stopped: Cell::new(false),
+    }
+}
+
+fn releaser_entry<C>(task: Task<C>, mailbox: Mailbox<ReleaserMessage>)
+where
+    C: Clone + Serialize + DeserializeOwned,
+{
+    let mut config = ProcessConfig::new().expect("periodic task needs to create configs");
+    config.set_scheduling(task.params);
+    let (period, deadline) = (task.params.period(), task.params.deadline());
+
+    let this = mailbox.this();
+    let mut next_release = Instant::now();
+    let mut next_job = 0u64;
+    // Release time of every job that is running and hasn't missed its
+    // deadline. Missed jobs are dropped, they may never finish.
+    let mut running: BTreeMap<u64, Instant> = BTreeMap::new();
+
+    loop {
+        let next_deadline = running.values().map(|release| *release + deadline).min();
+        let wake = next_deadline.map_or(next_release, |deadline| deadline.min(next_release));
+
+        let now = Instant::now();
+        if wake > now {
+            match mailbox.receive_timeout(wake - now) {
+                MailboxResult::Message(ReleaserMessage::Done(job)) => {
+                    if let Some(release) = running.remove(&job) {
+                        let response_time = release.elapsed();
+                        if response_time > deadline {
+                            report(&task, JobReport::Late { job, response_time });
+                        }
+                    }
+                    continue;
+                }
+                MailboxResult::Message(ReleaserMessage::Stop) => return,
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        running.retain(|job, release| {
+            let missed = now >= *release + deadline;
+            if missed {
+                report(&task, JobReport::Missed { job: *job });
+            }
+            !missed
+        });
+
+        if now >= next_release {
+            let job = next_job;
+            next_job += 1;
+            running.insert(job, next_release);
+            next_release += period;
+            Process::<()>::spawn_config(
+                &config,
+                (task.capture.clone(), task.body, this.clone(), job),
+                job_entry::<C>,
+            );
+        }
+    }
+}
+
+fn report<C>(task: &Task<C>, report: JobReport) {
+    task.spawner.tag_send(task.tag, report);
+}
+
+fn job_entry<C>(
+    (capture, body, releaser, job): (C, usize, Process<ReleaserMessage>, u64),
+    _: Mailbox<()>,
+) where
+    C: Serialize + DeserializeOwned,
+{
+    let body: fn(C) = unsafe { std::mem::transmute(body) };
+    body(capture);
+    releaser.send(ReleaserMessage::Done(job));
+}
@@ -0,0 +1,252 @@
+//! Session types for multi-step conversations between two processes.
+//!
+//! A protocol is described from the point of view of one side, for example
+//! `Recv<String, Send<usize, End>>` for a process that receives a string and
+//! answers with a number. The spawning process gets the dual protocol,
+//! `Send<String, Recv<usize, End>>`, so the compiler checks that both sides
+//! follow the same conversation.
+//!
+//! ```no_run
+//! use hyperwasm::protocol::{End, Protocol, Recv, Send};
+//!
+//! type Counter = Recv<String, Send<usize, End>>;
+//!
+//! let (child, _tag) = hyperwasm::spawn_link!(|protocol: Protocol<Counter>| {
+//!     let (protocol, text) = protocol.receive();
+//!     protocol.send(text.len()).close();
+//! });
+//! let (child, len) = child.send("hello".to_owned()).receive();
+//! child.close();
+//! assert_eq!(len, 5);
+//! ```
+use std::{any::TypeId, marker::PhantomData};
+
+use crate::{
+    function::process::{IntoProcess, Process},
+    host,
+    serializer::{Bincode, Serializer},
+    tag::Tag,
+    Mailbox, ProcessConfig,
+};
+
+/// One side of a conversation that follows protocol `P`.
+///
+/// Every step consumes the value and returns the protocol for the next step.
+/// Dropping it before reaching [`End`] or [`TaskEnd`] panics.
+pub struct Protocol<P: 'static, S = Bincode> {
+    peer: Process<(), S>,
+    tag: Tag,
+    phantom: PhantomData<P>,
+}
+
+/// Send a value of type `A`, then continue with `P`.
+pub struct Send<A, P>(PhantomData<(A, P)>);
+/// Receive a value of type `A`, then continue with `P`.
+pub struct Recv<A, P>(PhantomData<(A, P)>);
+/// Pick whether the conversation continues with `P` or with `Q`.
+pub struct Choose<P, Q>(PhantomData<(P, Q)>);
+/// Let the other side pick whether the conversation continues with `P` or `Q`.
+pub struct Offer<P, Q>(PhantomData<(P, Q)>);
+/// The end of a conversation.
+pub struct End;
+/// The end of a conversation started by a `spawn_link!(@task ..)`.
+pub struct TaskEnd;
+
+/// Links a protocol to the one the other side follows.
+pub trait HasDual {
+    type Dual;
+}
+
+impl HasDual for End {
+    type Dual = End;
+}
+
+impl HasDual for TaskEnd {
+    type Dual = TaskEnd;
+}
+
+impl<A, P: HasDual> HasDual for Send<A, P> {
+    type Dual = Recv<A, P::Dual>;
+}
+
+impl<A, P: HasDual> HasDual for Recv<A, P> {
+    type Dual = Send<A, P::Dual>;
+}
+
+impl<P: HasDual, Q: HasDual> HasDual for Choose<P, Q> {
+    type Dual = Offer<P::Dual, Q::Dual>;
+}
+
+impl<P: HasDual, Q: HasDual> HasDual for Offer<P, Q> {
+    type Dual = Choose<P::Dual, Q::Dual>;
+}
+
+/// The branch picked by the other side of an [`Offer`].
+pub enum Branch<L, R> {
+    Left(L),
+    Right(R),
+}
+
+impl<P: 'static, S> Protocol<P, S> {
+    fn from_parts(peer: Process<(), S>, tag: Tag) -> Self {
+        Self {
+            peer,
+            tag,
+            phantom: PhantomData,
+        }
+    }
+
+    fn cast<P2: 'static>(self) -> Protocol<P2, S> {
+        let next = Protocol::from_parts(self.peer.clone(), self.tag);
+        std::mem::forget(self);
+        next
+    }
+
+    fn send_<A>(&self, message: A)
+    where
+        S: Serializer<A>,
+    {
+        // Cast the peer to the message type, the id is all that matters.
+        let peer = Process::<A, S>::new(self.peer.node_id(), self.peer.id());
+        peer.tag_send(self.tag, message);
+    }
+
+    fn receive_<A>(&self) -> A
+    where
+        S: Serializer<A>,
+    {
+        unsafe { Mailbox::<A, S>::new() }.tag_receive(&[self.tag])
+    }
+}
+
+impl<S> Protocol<End, S> {
+    /// Finish the conversation.
+    pub fn close(self) {}
+}
+
+impl<A, P: 'static, S> Protocol<Send<A, P>, S>
+where
+    S: Serializer<A>,
+{
+    #[must_use]
+    pub fn send(self, message: A) -> Protocol<P, S> {
+        self.send_(message);
+        self.cast()
+    }
+}
+
+impl<A, P: 'static, S> Protocol<Recv<A, P>, S>
+where
+    S: Serializer<A>,
+{
+    #[must_use]
+    pub fn receive(self) -> (Protocol<P, S>, A) {
+        let message = self.receive_();
+        (self.cast(), message)
+    }
+}
+
+impl<A, S> Protocol<Recv<A, TaskEnd>, S>
+where
+    S: Serializer<A>,
+{
+    /// Wait for the result of a `spawn_link!(@task ..)`.
+    pub fn result(self) -> A {
+        self.receive().1
+    }
+}
+
+impl<P: 'static, Q: 'static, S> Protocol<Choose<P, Q>, S>
+where
+    S: Serializer<bool>,
+{
+    #[must_use]
+    pub fn select_left(self) -> Protocol<P, S> {
+        self.send_(true);
+        self.cast()
+    }
+
+    #[must_use]
+    pub fn select_right(self) -> Protocol<Q, S> {
+        self.send_(false);
+        self.cast()
+    }
+}
+
+impl<P: 'static, Q: 'static, S> Protocol<Offer<P, Q>, S>
+where
+    S: Serializer<bool>,
+{
+    #[must_use]
+    pub fn offer(self) -> Branch<Protocol<P, S>, Protocol<Q, S>> {
+        if self.receive_::<bool>() {
+            Branch::Left(self.cast())
+        } else {
+            Branch::Right(self.cast())
+        }
+    }
+}
+
+impl<P: 'static, S> Drop for Protocol<P, S> {
+    fn drop(&mut self) {
+        let finished = TypeId::of::<P>() == TypeId::of::<End>()
+            || TypeId::of::<P>() == TypeId::of::<TaskEnd>();
+        if !finished && !std::thread::panicking() {
+            panic!(
+                "Protocol dropped before reaching `End` or `TaskEnd` (currently: {})",
+                std::any::type_name::<P>()
+            );
+        }
+    }
+}
+
+impl<P: 'static, S> IntoProcess<P, S> for Protocol<P, S>
+where
+    P: HasDual,
+    <P as HasDual>::Dual: 'static,
+{
+    type Process = Protocol<<P as HasDual>::Dual, S>;
+
+    fn spawn<C>(
+        capture: C,
+        entry: fn(C, Self),
+        link: Option<Tag>,
+        config: Option<&ProcessConfig>,
+        node: Option<u64>,
+    ) -> Self::Process
+    where
+        S: Serializer<C>,
+    {
+        let entry = entry as usize;
+        let node_id = node.unwrap_or_else(host::node_id);
+        let tag = Tag::new();
+
+        match host::spawn(Some(node_id), config, link, type_helper_wrapper::<C, P, S>, entry) {
+            Ok(id) => {
+                // The child first learns who it is talking to, then gets the capture.
+                let child = Process::<(Process<()>, Tag)>::new(node_id, id);
+                child.send((Process::this(), tag));
+                if std::mem::size_of::<C>() != 0 {
+                    Process::<C, S>::new(node_id, id).send(capture);
+                }
+                Protocol::from_parts(Process::new(node_id, id), tag)
+            }
+            Err(err) => panic!("Failed to spawn a process: {}", err),
+        }
+    }
+}
+
+fn type_helper_wrapper<C, P: 'static, S>(function: usize)
+where
+    S: Serializer<C>,
+{
+    let (parent, tag) = unsafe { Mailbox::<(Process<()>, Tag)>::new() }.receive();
+    let captured = if std::mem::size_of::<C>() == 0 {
+        unsafe { std::mem::MaybeUninit::<C>::zeroed().assume_init() }
+    } else {
+        unsafe { Mailbox::<C, S>::new() }.receive()
+    };
+    let protocol = Protocol::from_parts(Process::new(parent.node_id(), parent.id()), tag);
+    let function: fn(C, Protocol<P, S>) = unsafe { std::mem::transmute(function) };
+    function(captured, protocol);
+}
@@ -0,0 +1,147 @@
+//! Request/reply round trips between processes.
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    host::{self, api::message},
+    mailbox::{LINK_DIED, TIMEOUT},
+    serializer::{write_message, Bincode, DecodeError, EncodeError, Serializer},
+    tag::Tag,
+    Mailbox, Process,
+};
+
+/// A message of type `Q` that expects a reply of type `R`.
+///
+/// Sent by [`Process::request`] and answered with [`Request::reply`] or
+/// [`Mailbox::answer`]. The reply is tagged with a tag unique to this request,
+/// so it can't be confused with any other message the requester receives.
+#[derive(Serialize, Deserialize)]
+#[serde(bound(serialize = "Q: Serialize", deserialize = "Q: DeserializeOwned"))]
+pub struct Request<Q, R, S = Bincode> {
+    message: Q,
+    sender: Process<R, S>,
+    tag: Tag,
+}
+
+impl<Q, R, S> Request<Q, R, S> {
+    /// A request from the current process, with a fresh reply tag.
+    pub(crate) fn new(message: Q) -> Self {
+        Self {
+            message,
+            sender: Process::this(),
+            tag: Tag::new(),
+        }
+    }
+
+    pub(crate) fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    pub fn message(&self) -> &Q {
+        &self.message
+    }
+
+    /// The process waiting for the reply.
+    pub fn sender(&self) -> &Process<R, S> {
+        &self.sender
+    }
+
+    pub fn into_message(self) -> Q {
+        self.message
+    }
+
+    /// Split off the message, keeping what is needed to reply.
+    pub(crate) fn into_parts(self) -> (Q, Request<(), R, S>) {
+        let reply_to = Request {
+            message: (),
+            sender: self.sender,
+            tag: self.tag,
+        };
+        (self.message, reply_to)
+    }
+}
+
+impl<Q, R, S> Request<Q, R, S>
+where
+    S: Serializer<R>,
+{
+    /// Send `response` back to the requesting process.
+    pub fn reply(self, response: R) {
+        send_reply(&self.sender, self.tag, response);
+    }
+}
+
+fn send_reply<R, S>(sender: &Process<R, S>, tag: Tag, response: R)
+where
+    S: Serializer<R>,
+{
+    write_message::<R, S>(tag, &response).unwrap();
+
+    host::send(sender.node_id(), sender.id());
+}
+
+#[derive(Error, Debug)]
+pub enum RequestError {
+    #[error("no reply arrived before the timeout")]
+    Timeout,
+    #[error("a linked process died while waiting for the reply")]
+    LinkDied(Tag),
+    #[error("failed to encode the request: {0}")]
+    Encode(#[from] EncodeError),
+    #[error("failed to decode the reply: {0}")]
+    Decode(#[from] DecodeError),
+}
+
+impl<Q, R, S> Process<Request<Q, R, S>, S>
+where
+    S: Serializer<Request<Q, R, S>> + Serializer<R>,
+{
+    /// Send `message` and wait up to `timeout` for the reply.
+    ///
+    /// Messages already waiting in the mailbox are not searched, the host
+    /// only looks at messages arriving after the request was sent.
+    pub fn request(&self, message: Q, timeout: Duration) -> Result<R, RequestError> {
+        let request = Request::new(message);
+        let tag = request.tag();
+        send_request(self, request, tag, timeout)
+    }
+}
+
+/// Send `message` tagged with `tag` and wait up to `timeout` for a reply
+/// carrying the same tag.
+pub(crate) fn send_request<M, R, S>(
+    process: &Process<M, S>,
+    message: M,
+    tag: Tag,
+    timeout: Duration,
+) -> Result<R, RequestError>
+where
+    S: Serializer<M> + Serializer<R>,
+{
+    write_message::<M, S>(tag, &message)?;
+
+    let timeout_ms = timeout.as_millis() as u64;
+    match host::send_receive_skip_search(process.node_id(), process.id(), timeout_ms) {
+        LINK_DIED => Err(RequestError::LinkDied(unsafe { Tag::from(message::get_tag()) })),
+        TIMEOUT => Err(RequestError::Timeout),
+        _ => Ok(<S as Serializer<R>>::decode()?),
+    }
+}
+
+impl<Q, R, S> Mailbox<Request<Q, R, S>, S>
+where
+    S: Serializer<Request<Q, R, S>> + Serializer<R>,
+{
+    /// Wait for the next request and answer it with the result of `handler`.
+    #[track_caller]
+    pub fn answer<F>(&self, handler: F)
+    where
+        F: FnOnce(Q) -> R,
+    {
+        let request = self.receive();
+        let response = handler(request.message);
+        send_reply(&request.sender, request.tag, response);
+    }
+}
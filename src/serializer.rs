@@ -1,7 +1,17 @@
 //! Serializer implementations for messages.
+use std::{
+    cell::RefCell,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use serde::de::DeserializeOwned;
 use thiserror::Error;
 
-use crate::host::api::message;
+use crate::{host::api::message, tag::Tag};
 
 #[derive(Error, Debug)]
 pub enum EncodeError {
@@ -45,14 +55,87 @@ pub enum DecodeError {
     IO(#[from] std::io::Error),
     #[error("deserialization failed: {0}")]
     Custom(String),
+    #[error("expected a message of type {expected} (fingerprint {expected_fingerprint:#x}), found fingerprint {found:#x}")]
+    TypeMismatch {
+        expected: &'static str,
+        expected_fingerprint: u64,
+        found: u64,
+    },
+    #[error("can't decode version {found} of {name} as version {expected}")]
+    VersionMismatch {
+        name: &'static str,
+        expected: u32,
+        found: u32,
+    },
 }
 
 
 pub trait Serializer<M> {
-    fn encode(message: &M) -> Result<(), EncodeError>;
+    /// Append the encoding of `message` to `buffer`.
+    fn encode(message: &M, buffer: &mut Vec<u8>) -> Result<(), EncodeError>;
     fn decode() -> Result<M, DecodeError>;
 }
 
+/// Serializers that can decode any serde type, used by [`Versioned`] to
+/// decode older versions of a [`Schema`].
+pub trait AnyDecoder {
+    fn decode_any<T: DeserializeOwned>() -> Result<T, DecodeError>;
+}
+
+/// Start a new message carrying `tag` and encode `message` into it.
+///
+/// The message is encoded once into a local buffer, so the host buffer is
+/// created with its final size and filled with a single write.
+pub(crate) fn write_message<M, S>(tag: Tag, message: &M) -> Result<(), EncodeError>
+where
+    S: Serializer<M>,
+{
+    let mut buffer = Vec::new();
+    PENDING_TCP_STREAMS.with(|pending| *pending.borrow_mut() = Some(Vec::new()));
+    let result = S::encode(message, &mut buffer);
+    let streams = PENDING_TCP_STREAMS.with(|pending| pending.borrow_mut().take()).unwrap_or_default();
+    // If encoding failed, the streams stay with their handles.
+    result?;
+    unsafe {
+        message::create_data(tag.id(), buffer.len() as u64);
+        for (index, (id, consumed)) in streams.into_iter().enumerate() {
+            let pushed = message::push_tcp_stream(id);
+            debug_assert_eq!(pushed, index as u64);
+            consumed.store(true, Ordering::Relaxed);
+        }
+        message::write_data(buffer.as_ptr(), buffer.len());
+    }
+    Ok(())
+}
+
+// A tcp stream id and the `consumed` flag of its handle.
+type PendingTcpStream = (u64, Arc<AtomicBool>);
+
+thread_local! {
+    // Tcp streams serialized into the message being encoded, `None` outside
+    // of `write_message`. They are pushed once the host buffer exists.
+    static PENDING_TCP_STREAMS: RefCell<Option<Vec<PendingTcpStream>>> = const { RefCell::new(None) };
+}
+
+/// Add the tcp stream `id` to the message being encoded and return its index
+/// in the message.
+///
+/// `consumed` is set once the message is built. Fails if no message is being
+/// encoded or the stream is already part of it.
+pub(crate) fn move_tcp_stream(id: u64, consumed: &Arc<AtomicBool>) -> Result<u64, &'static str> {
+    PENDING_TCP_STREAMS.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let pending = pending
+            .as_mut()
+            .ok_or("TcpStream can only be serialized as part of a message")?;
+        if pending.iter().any(|(pending_id, _)| *pending_id == id) {
+            return Err("TcpStream is already part of this message");
+        }
+        pending.push((id, consumed.clone()));
+        Ok(pending.len() as u64 - 1)
+    })
+}
+
 
 #[derive(Hash, Debug)]
 pub struct Bincode {}
@@ -61,8 +144,8 @@ impl<M> Serializer<M> for Bincode
 where
     M: serde::Serialize + serde::de::DeserializeOwned,
 {
-    fn encode(message: &M) -> Result<(), EncodeError> {
-        Ok(bincode::serialize_into(MessageRw {}, message)?)
+    fn encode(message: &M, buffer: &mut Vec<u8>) -> Result<(), EncodeError> {
+        Ok(bincode::serialize_into(buffer, message)?)
     }
 
     fn decode() -> Result<M, DecodeError> {
@@ -70,6 +153,12 @@ where
     }
 }
 
+impl AnyDecoder for Bincode {
+    fn decode_any<T: DeserializeOwned>() -> Result<T, DecodeError> {
+        Ok(bincode::deserialize_from(MessageRw {})?)
+    }
+}
+
 
 #[cfg(feature = "msgpack_serializer")]
 #[cfg_attr(docsrs, doc(cfg(feature = "msgpack_serializer")))]
@@ -82,10 +171,8 @@ impl<M> Serializer<M> for MessagePack
 where
     M: serde::Serialize + serde::de::DeserializeOwned,
 {
-    fn encode(message: &M) -> Result<(), EncodeError> {
-        use std::io::Write;
-        let data = rmp_serde::to_vec(message)?;
-        Ok(MessageRw {}.write_all(&data)?)
+    fn encode(message: &M, buffer: &mut Vec<u8>) -> Result<(), EncodeError> {
+        Ok(rmp_serde::encode::write(buffer, message)?)
     }
 
     fn decode() -> Result<M, DecodeError> {
@@ -93,6 +180,14 @@ where
     }
 }
 
+#[cfg(feature = "msgpack_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack_serializer")))]
+impl AnyDecoder for MessagePack {
+    fn decode_any<T: DeserializeOwned>() -> Result<T, DecodeError> {
+        Ok(rmp_serde::decode::from_read(MessageRw {})?)
+    }
+}
+
 
 #[cfg(feature = "json_serializer")]
 #[cfg_attr(docsrs, doc(cfg(feature = "json_serializer")))]
@@ -105,10 +200,8 @@ impl<M> Serializer<M> for Json
 where
     M: serde::Serialize + serde::de::DeserializeOwned,
 {
-    fn encode(message: &M) -> Result<(), EncodeError> {
-        use std::io::Write;
-        let data = serde_json::to_vec(message)?;
-        Ok(MessageRw {}.write_all(&data)?)
+    fn encode(message: &M, buffer: &mut Vec<u8>) -> Result<(), EncodeError> {
+        Ok(serde_json::to_writer(buffer, message)?)
     }
 
     fn decode() -> Result<M, DecodeError> {
@@ -116,6 +209,14 @@ where
     }
 }
 
+#[cfg(feature = "json_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json_serializer")))]
+impl AnyDecoder for Json {
+    fn decode_any<T: DeserializeOwned>() -> Result<T, DecodeError> {
+        Ok(serde_json::from_reader(MessageRw {})?)
+    }
+}
+
 
 #[cfg(feature = "protobuf_serializer")]
 #[cfg_attr(docsrs, doc(cfg(feature = "protobuf_serializer")))]
@@ -128,11 +229,8 @@ impl<M> Serializer<M> for ProtocolBuffers
 where
     M: protobuf::Message,
 {
-    fn encode(message: &M) -> Result<(), EncodeError> {
-        use std::io::Write;
-        let mut data = Vec::new();
-        message.write_to_vec(&mut data)?;
-        Ok(MessageRw {}.write_all(&data)?)
+    fn encode(message: &M, buffer: &mut Vec<u8>) -> Result<(), EncodeError> {
+        Ok(message.write_to_vec(buffer)?)
     }
 
     fn decode() -> Result<M, DecodeError> {
@@ -141,6 +239,303 @@ where
 }
 
 
+/// Serializers that can decode from any reader, required by [`Compressed`].
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+pub trait StreamSerializer<M>: Serializer<M> {
+    fn decode_from<R: std::io::Read>(reader: R) -> Result<M, DecodeError>;
+}
+
+/// Serializers that can decode any serde type from any reader, required by
+/// [`Compressed`] to be an [`AnyDecoder`].
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+pub trait AnyStreamDecoder: AnyDecoder {
+    fn decode_any_from<T: DeserializeOwned, R: std::io::Read>(reader: R) -> Result<T, DecodeError>;
+}
+
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+impl AnyStreamDecoder for Bincode {
+    fn decode_any_from<T: DeserializeOwned, R: std::io::Read>(reader: R) -> Result<T, DecodeError> {
+        Ok(bincode::deserialize_from(reader)?)
+    }
+}
+
+#[cfg(all(feature = "compression", feature = "msgpack_serializer"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "compression", feature = "msgpack_serializer"))))]
+impl AnyStreamDecoder for MessagePack {
+    fn decode_any_from<T: DeserializeOwned, R: std::io::Read>(reader: R) -> Result<T, DecodeError> {
+        Ok(rmp_serde::decode::from_read(reader)?)
+    }
+}
+
+#[cfg(all(feature = "compression", feature = "json_serializer"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "compression", feature = "json_serializer"))))]
+impl AnyStreamDecoder for Json {
+    fn decode_any_from<T: DeserializeOwned, R: std::io::Read>(reader: R) -> Result<T, DecodeError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+impl<M> StreamSerializer<M> for Bincode
+where
+    M: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn decode_from<R: std::io::Read>(reader: R) -> Result<M, DecodeError> {
+        Ok(bincode::deserialize_from(reader)?)
+    }
+}
+
+#[cfg(all(feature = "compression", feature = "msgpack_serializer"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "compression", feature = "msgpack_serializer"))))]
+impl<M> StreamSerializer<M> for MessagePack
+where
+    M: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn decode_from<R: std::io::Read>(reader: R) -> Result<M, DecodeError> {
+        Ok(rmp_serde::decode::from_read(reader)?)
+    }
+}
+
+#[cfg(all(feature = "compression", feature = "json_serializer"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "compression", feature = "json_serializer"))))]
+impl<M> StreamSerializer<M> for Json
+where
+    M: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn decode_from<R: std::io::Read>(reader: R) -> Result<M, DecodeError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+/// Codec used by [`Compressed`], decoding straight out of the message
+/// buffer.
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+pub trait Compression {
+    /// Header flag marking a payload compressed with this codec, never `0`.
+    const FLAG: u8;
+    type Decoder: std::io::Read;
+
+    /// Append the compressed form of `data` to `buffer`.
+    fn compress(data: &[u8], buffer: &mut Vec<u8>) -> std::io::Result<()>;
+    fn decoder(reader: MessageRw) -> Self::Decoder;
+}
+
+/// LZ4 frame format, fast with a moderate ratio.
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+#[derive(Debug, Hash)]
+pub struct Lz4 {}
+
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+impl Compression for Lz4 {
+    const FLAG: u8 = 1;
+    type Decoder = lz4_flex::frame::FrameDecoder<MessageRw>;
+
+    fn compress(data: &[u8], buffer: &mut Vec<u8>) -> std::io::Result<()> {
+        use std::io::Write;
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(buffer);
+        encoder.write_all(data)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    fn decoder(reader: MessageRw) -> Self::Decoder {
+        lz4_flex::frame::FrameDecoder::new(reader)
+    }
+}
+
+/// Raw DEFLATE, slower than [`Lz4`] with a better ratio.
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+#[derive(Debug, Hash)]
+pub struct Deflate {}
+
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+impl Compression for Deflate {
+    const FLAG: u8 = 2;
+    type Decoder = flate2::read::DeflateDecoder<MessageRw>;
+
+    fn compress(data: &[u8], buffer: &mut Vec<u8>) -> std::io::Result<()> {
+        use std::io::Write;
+        let mut encoder = flate2::write::DeflateEncoder::new(buffer, flate2::Compression::default());
+        encoder.write_all(data)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    fn decoder(reader: MessageRw) -> Self::Decoder {
+        flate2::read::DeflateDecoder::new(reader)
+    }
+}
+
+/// Wraps the serializer `S`, compressing messages whose encoding is larger
+/// than `MIN_SIZE` bytes with `A`.
+///
+/// A one byte header tells the receiver whether the payload was
+/// compressed, so both forms decode with the same type.
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+#[derive(Debug, Hash)]
+pub struct Compressed<S = Bincode, A = Lz4, const MIN_SIZE: usize = 512> {
+    phantom: PhantomData<(S, A)>,
+}
+
+// Header flag of a payload stored as is.
+#[cfg(feature = "compression")]
+const UNCOMPRESSED: u8 = 0;
+
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+impl<M, S, A, const MIN_SIZE: usize> Serializer<M> for Compressed<S, A, MIN_SIZE>
+where
+    S: StreamSerializer<M>,
+    A: Compression,
+{
+    fn encode(message: &M, buffer: &mut Vec<u8>) -> Result<(), EncodeError> {
+        let header = buffer.len();
+        buffer.push(UNCOMPRESSED);
+        S::encode(message, buffer)?;
+        if buffer.len() - header - 1 > MIN_SIZE {
+            let payload = buffer.split_off(header + 1);
+            buffer[header] = A::FLAG;
+            A::compress(&payload, buffer)?;
+        }
+        Ok(())
+    }
+
+    fn decode() -> Result<M, DecodeError> {
+        Self::decode_with(|reader| S::decode_from(reader))
+    }
+}
+
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+impl<S, A, const MIN_SIZE: usize> AnyDecoder for Compressed<S, A, MIN_SIZE>
+where
+    S: AnyStreamDecoder,
+    A: Compression,
+{
+    fn decode_any<T: DeserializeOwned>() -> Result<T, DecodeError> {
+        Self::decode_with(|reader| S::decode_any_from(reader))
+    }
+}
+
+#[cfg(feature = "compression")]
+impl<S, A, const MIN_SIZE: usize> Compressed<S, A, MIN_SIZE>
+where
+    A: Compression,
+{
+    /// Read the header and pass the payload, decompressed if needed, to
+    /// `decode`.
+    fn decode_with<T>(
+        decode: impl FnOnce(&mut dyn std::io::Read) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError> {
+        use std::io::Read;
+        let mut flag = 0;
+        MessageRw {}.read_exact(std::slice::from_mut(&mut flag))?;
+        match flag {
+            UNCOMPRESSED => decode(&mut MessageRw {}),
+            flag if flag == A::FLAG => decode(&mut A::decoder(MessageRw {})),
+            flag => Err(DecodeError::Custom(format!("unknown compression flag {}", flag))),
+        }
+    }
+}
+
+
+/// Identity and schema version of a message type sent with [`Versioned`].
+pub trait Schema: Sized + 'static {
+    /// Name identifying the type on every node, e.g. `"sensors::Frame"`.
+    ///
+    /// Only the name is fingerprinted, so it must stay the same when the
+    /// type is moved or renamed in code.
+    const NAME: &'static str;
+    const VERSION: u32;
+
+    /// Decode the payload of an older `version` into the current type, or
+    /// return `None` if that version can't be upgraded.
+    ///
+    /// Decode the old type with `S`, the serializer the message was sent
+    /// with, e.g. `1 => Some(S::decode_any::<FrameV1>().map(Frame::from))`.
+    fn upgrade<S: AnyDecoder>(version: u32) -> Option<Result<Self, DecodeError>> {
+        let _ = version;
+        None
+    }
+}
+
+/// Wraps the serializer `S` with a header carrying the type fingerprint and
+/// schema version, so a receiver expecting another type or an unknown
+/// version fails with [`DecodeError::TypeMismatch`] or
+/// [`DecodeError::VersionMismatch`] instead of decoding garbage.
+///
+/// Older versions are upgraded with [`Schema::upgrade`], which needs `S` to
+/// be an [`AnyDecoder`].
+#[derive(Debug, Hash)]
+pub struct Versioned<S = Bincode> {
+    serializer: PhantomData<S>,
+}
+
+const VERSIONED_HEADER_LEN: usize = 12;
+
+impl<M, S> Serializer<M> for Versioned<S>
+where
+    M: Schema,
+    S: Serializer<M> + AnyDecoder,
+{
+    fn encode(message: &M, buffer: &mut Vec<u8>) -> Result<(), EncodeError> {
+        buffer.extend_from_slice(&fingerprint(M::NAME).to_le_bytes());
+        buffer.extend_from_slice(&M::VERSION.to_le_bytes());
+        S::encode(message, buffer)
+    }
+
+    fn decode() -> Result<M, DecodeError> {
+        use std::io::Read;
+        let mut header = [0; VERSIONED_HEADER_LEN];
+        MessageRw {}.read_exact(&mut header)?;
+        let found = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let version = u32::from_le_bytes(header[8..].try_into().unwrap());
+        if found != fingerprint(M::NAME) {
+            return Err(DecodeError::TypeMismatch {
+                expected: M::NAME,
+                expected_fingerprint: fingerprint(M::NAME),
+                found,
+            });
+        }
+        if version == M::VERSION {
+            return S::decode();
+        }
+        match M::upgrade::<S>(version) {
+            Some(message) => message,
+            None => Err(DecodeError::VersionMismatch {
+                name: M::NAME,
+                expected: M::VERSION,
+                found: version,
+            }),
+        }
+    }
+
+}
+
+/// 64 bit FNV-1a hash of a type name.
+const fn fingerprint(name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut hash = 0xcbf2_9ce4_8422_2325;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+
 #[derive(Debug, Hash)]
 pub struct MessageRw {}
 
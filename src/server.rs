@@ -0,0 +1,146 @@
+//! Long-running processes that own a state and serve calls and casts.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use hyperwasm::{serializer::Bincode, server::Server};
+//!
+//! struct Counter;
+//!
+//! impl Server for Counter {
+//!     type Arg = u64;
+//!     type State = u64;
+//!     type Call = ();
+//!     type Response = u64;
+//!     type Cast = u64;
+//!     type Serializer = Bincode;
+//!
+//!     fn init(start: u64) -> u64 {
+//!         start
+//!     }
+//!
+//!     fn handle_call(count: &mut u64, _: ()) -> u64 {
+//!         *count
+//!     }
+//!
+//!     fn handle_cast(count: &mut u64, add: u64) {
+//!         *count += add;
+//!     }
+//! }
+//!
+//! let counter = Counter::start(0);
+//! counter.cast(5);
+//! assert_eq!(counter.call((), Duration::from_secs(1)).unwrap(), 5);
+//! counter.shutdown();
+//! ```
+use std::{fmt, time::Duration};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    request::{send_request, Request, RequestError},
+    serializer::Serializer,
+    Mailbox, Process,
+};
+
+/// A process owning a [`State`](Server::State), changed only by the calls and
+/// casts it receives.
+pub trait Server: Sized + 'static {
+    /// Argument passed to [`init`](Server::init) in the server process.
+    type Arg;
+    type State;
+    /// Requests answered by [`handle_call`](Server::handle_call).
+    type Call;
+    type Response;
+    /// Messages handled by [`handle_cast`](Server::handle_cast), without a reply.
+    type Cast;
+    type Serializer: Serializer<Self::Arg> + Serializer<ServerMessage<Self>> + Serializer<Self::Response>;
+
+    /// Create the state, runs in the server process before any message is handled.
+    fn init(arg: Self::Arg) -> Self::State;
+
+    fn handle_call(state: &mut Self::State, request: Self::Call) -> Self::Response;
+
+    fn handle_cast(state: &mut Self::State, message: Self::Cast);
+
+    /// Runs after [`ServerRef::shutdown`], before the server process exits.
+    fn terminate(_state: Self::State) {}
+
+    /// Spawn the server process.
+    fn start(arg: Self::Arg) -> ServerRef<Self> {
+        let process = Process::spawn(arg, server_entry::<Self>);
+        ServerRef { process }
+    }
+}
+
+/// Messages understood by a server process.
+#[derive(Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T::Call: Serialize, T::Cast: Serialize",
+    deserialize = "T::Call: DeserializeOwned, T::Cast: DeserializeOwned"
+))]
+pub enum ServerMessage<T: Server> {
+    Call(Request<T::Call, T::Response, T::Serializer>),
+    Cast(T::Cast),
+    Shutdown,
+}
+
+/// Handle to a running server.
+pub struct ServerRef<T: Server> {
+    process: Process<ServerMessage<T>, T::Serializer>,
+}
+
+impl<T: Server> ServerRef<T> {
+    /// Send `request` and wait up to `timeout` for the response.
+    pub fn call(&self, request: T::Call, timeout: Duration) -> Result<T::Response, RequestError> {
+        let request = Request::new(request);
+        let tag = request.tag();
+        send_request(&self.process, ServerMessage::Call(request), tag, timeout)
+    }
+
+    /// Send `message` without waiting for it to be handled.
+    pub fn cast(&self, message: T::Cast) {
+        self.process.send(ServerMessage::Cast(message));
+    }
+
+    /// Ask the server to run [`Server::terminate`] and exit.
+    pub fn shutdown(&self) {
+        self.process.send(ServerMessage::Shutdown);
+    }
+
+    pub fn process(&self) -> &Process<ServerMessage<T>, T::Serializer> {
+        &self.process
+    }
+}
+
+impl<T: Server> Clone for ServerRef<T> {
+    fn clone(&self) -> Self {
+        Self {
+            process: self.process.clone(),
+        }
+    }
+}
+
+impl<T: Server> fmt::Debug for ServerRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerRef")
+            .field("server", &std::any::type_name::<T>())
+            .field("process", &self.process)
+            .finish()
+    }
+}
+
+fn server_entry<T: Server>(arg: T::Arg, mailbox: Mailbox<ServerMessage<T>, T::Serializer>) {
+    let mut state = T::init(arg);
+    loop {
+        match mailbox.receive() {
+            ServerMessage::Call(request) => {
+                let (request, reply_to) = request.into_parts();
+                let response = T::handle_call(&mut state, request);
+                reply_to.reply(response);
+            }
+            ServerMessage::Cast(message) => T::handle_cast(&mut state, message),
+            ServerMessage::Shutdown => break,
+        }
+    }
+    T::terminate(state);
+}
@@ -0,0 +1,342 @@
+//! Chunked streaming of payloads too large for a single message.
+//!
+//! [`Process::send_stream`] splits a reader into raw chunks carrying a tag
+//! picked by the receiver, and [`Mailbox::receive_stream`] hands them out
+//! as a [`StreamReader`]. At most [`WINDOW`] chunks are in flight, the
+//! receiver acknowledges each chunk once it moves past it, so neither side
+//! holds more than a few chunks in memory.
+//!
+//! The current chunk stays in the host's message buffer while it is read,
+//! so no other message may be received while a [`StreamReader`] is used.
+//! Dropping the reader takes the rest of the stream out of the mailbox.
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    time::Duration,
+};
+
+use crate::{
+    host::{self, api::message},
+    mailbox::{read_raw, receive_with},
+    serializer::Serializer,
+    tag::Tag,
+    Mailbox, MailboxResult, Process,
+};
+
+/// Payload bytes per chunk.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+/// Chunks sent ahead of the receiver's acknowledgements.
+pub const WINDOW: usize = 4;
+
+// How long a dropped reader waits for each remaining chunk if it has no
+// read timeout, so a dead sender can't block the drop forever.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MAGIC: &[u8; 4] = b"HWST";
+const HEADER_LEN: usize = MAGIC.len() + 24;
+
+// First byte of every chunk.
+const DATA: u8 = 0;
+const END: u8 = 1;
+const ABORT: u8 = 2;
+
+// First byte of every message going back to the sender.
+const CANCEL: u8 = 0;
+const ACK: u8 = 1;
+const READY: u8 = 2;
+
+impl<M, S> Process<M, S>
+where
+    S: Serializer<M>,
+{
+    /// Stream everything `reader` yields to this process, which has to pick
+    /// it up with [`Mailbox::receive_stream`].
+    ///
+    /// Blocks until the receiver consumed the whole stream and returns the
+    /// number of bytes sent. Fails with [`io::ErrorKind::BrokenPipe`] if the
+    /// receiver drops its reader before reading all data, and with
+    /// [`io::ErrorKind::TimedOut`] if it doesn't answer the handshake or
+    /// acknowledge a chunk within `timeout`.
+    pub fn send_stream(&self, mut reader: impl Read, timeout: Duration) -> io::Result<u64> {
+        let own_tag = Tag::new();
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&host::node_id().to_le_bytes());
+        header.extend_from_slice(&host::process_id().to_le_bytes());
+        header.extend_from_slice(&own_tag.id().to_le_bytes());
+        self.send_raw_tagged(Tag::STREAM_HEADER, &header);
+
+        let reply = wait_for_receiver(own_tag, timeout)?;
+        let chunk_tag = match reply.as_slice() {
+            [READY, tag @ ..] if tag.len() == 8 => {
+                Tag::from(i64::from_le_bytes(tag.try_into().unwrap()))
+            }
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid stream handshake")),
+        };
+
+        let mut credits = WINDOW;
+        let mut sent = 0;
+        let mut buffer = vec![0; CHUNK_SIZE];
+        let result = loop {
+            if credits == 0 {
+                match take_ack(own_tag, timeout) {
+                    Ok(credit) => credits += credit,
+                    Err(err) => break Err(err),
+                }
+            }
+            let len = match fill(&mut reader, &mut buffer) {
+                Ok(len) => len,
+                Err(err) => break Err(err),
+            };
+            if len == 0 {
+                break Ok(());
+            }
+            self.send_chunk(chunk_tag, DATA, &buffer[..len]);
+            credits -= 1;
+            sent += len as u64;
+        };
+        // The end marker takes a credit as well.
+        let result = result.and_then(|()| {
+            if credits == 0 {
+                credits += take_ack(own_tag, timeout)?;
+            }
+            Ok(())
+        });
+        // Until the end marker is sent, the receiver waits for another
+        // chunk, tell it the stream is over.
+        if let Err(err) = result {
+            self.send_chunk(chunk_tag, ABORT, &[]);
+            return Err(err);
+        }
+        self.send_chunk(chunk_tag, END, &[]);
+        credits -= 1;
+
+        // Once all credits are back the receiver has read everything.
+        while credits < WINDOW {
+            credits += take_ack(own_tag, timeout)?;
+        }
+        Ok(sent)
+    }
+
+    fn send_chunk(&self, tag: Tag, kind: u8, data: &[u8]) {
+        unsafe {
+            message::create_data(tag.id(), data.len() as u64 + 1);
+            message::write_data(&kind, 1);
+            message::write_data(data.as_ptr(), data.len());
+        }
+        host::send(self.node_id(), self.id());
+    }
+}
+
+impl<M, S, L> Mailbox<M, S, L>
+where
+    S: Serializer<M>,
+{
+    /// Wait for the start of a stream sent with [`Process::send_stream`].
+    ///
+    /// Stream headers carry a tag of their own, other messages stay in the
+    /// mailbox.
+    pub fn receive_stream(&self) -> io::Result<StreamReader> {
+        let header = unwrap_raw(receive_with(&[Tag::STREAM_HEADER], None, read_raw))?;
+        if header.len() != HEADER_LEN || !header.starts_with(MAGIC) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "message is not a stream header"));
+        }
+        let field = |i: usize| -> [u8; 8] {
+            let start = MAGIC.len() + i * 8;
+            header[start..start + 8].try_into().unwrap()
+        };
+        let sender_tag = Tag::from(i64::from_le_bytes(field(2)));
+
+        let reader = StreamReader {
+            sender: Process::new(u64::from_le_bytes(field(0)), u64::from_le_bytes(field(1))),
+            sender_tag,
+            chunk_tag: Tag::new(),
+            timeout: None,
+            position: 0,
+            len: 0,
+            started: false,
+            finished: false,
+        };
+        let mut ready = vec![READY];
+        ready.extend_from_slice(&reader.chunk_tag.id().to_le_bytes());
+        reader.sender.send_raw_tagged(sender_tag, &ready);
+        Ok(reader)
+    }
+}
+
+/// Receiving end of a stream, see [`Mailbox::receive_stream`].
+///
+/// Seeking is limited to the chunk currently being read.
+#[derive(Debug)]
+pub struct StreamReader {
+    sender: Process<()>,
+    sender_tag: Tag,
+    chunk_tag: Tag,
+    timeout: Option<Duration>,
+    // Position and length of the current chunk's payload.
+    position: u64,
+    len: u64,
+    started: bool,
+    finished: bool,
+}
+
+impl StreamReader {
+    /// Sets how long a read waits for the next chunk, `None` blocks
+    /// indefinitely.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Move to the next chunk, returns `false` at the end of the stream.
+    fn next_chunk(&mut self) -> io::Result<bool> {
+        if self.started {
+            self.ack();
+        }
+        self.started = true;
+
+        match self.receive_chunk(self.timeout)? {
+            DATA => Ok(true),
+            END => {
+                self.ack();
+                Ok(false)
+            }
+            _ => Err(io::Error::other("sender aborted the stream")),
+        }
+    }
+
+    /// Receive the next chunk and return its kind.
+    fn receive_chunk(&mut self, timeout: Option<Duration>) -> io::Result<u8> {
+        unwrap_raw(receive_with(&[self.chunk_tag], timeout, || Ok(())))?;
+        let size = unsafe { message::data_size() };
+        let mut kind = 0;
+        if size == 0 || unsafe { message::read_data(&mut kind, 1) } != 1 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "empty stream chunk"));
+        }
+        match kind {
+            DATA => {
+                self.position = 0;
+                self.len = size - 1;
+            }
+            END | ABORT => {
+                self.position = 0;
+                self.len = 0;
+                self.finished = true;
+            }
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown stream chunk")),
+        }
+        Ok(kind)
+    }
+
+    fn ack(&self) {
+        self.sender.send_raw_tagged(self.sender_tag, &[ACK]);
+    }
+}
+
+impl Read for StreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while self.position == self.len {
+            if self.finished || !self.next_chunk()? {
+                return Ok(0);
+            }
+        }
+        let len = unsafe { message::read_data(buf.as_mut_ptr(), buf.len()) };
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl Seek for StreamReader {
+    /// Seek within the current chunk, [`SeekFrom::End`] is relative to its end.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+        };
+        match target {
+            Some(target) if target <= self.len => {
+                // Skip the chunk's kind byte.
+                unsafe { message::seek_data(target + 1) };
+                self.position = target;
+                Ok(target)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "can only seek within the current chunk",
+            )),
+        }
+    }
+}
+
+impl Drop for StreamReader {
+    /// Take the rest of the stream out of the mailbox.
+    ///
+    /// A reader dropped after the last data byte, but before it saw the end
+    /// marker, still completes the stream. If data is left unread the sender
+    /// is cancelled and the remaining chunks are discarded until it aborts.
+    fn drop(&mut self) {
+        let timeout = Some(self.timeout.unwrap_or(DRAIN_TIMEOUT));
+        let mut cancelled = false;
+        while !self.finished {
+            if !cancelled {
+                if self.position < self.len {
+                    self.sender.send_raw_tagged(self.sender_tag, &[CANCEL]);
+                    cancelled = true;
+                } else if self.started {
+                    self.ack();
+                }
+            }
+            self.started = true;
+            match self.receive_chunk(timeout) {
+                Ok(END) if !cancelled => self.ack(),
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+fn unwrap_raw<T>(result: MailboxResult<T>) -> io::Result<T> {
+    match result {
+        MailboxResult::Message(data) => Ok(data),
+        MailboxResult::LinkDied(_) => Err(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "a linked process died during the stream",
+        )),
+        MailboxResult::TimedOut => Err(io::ErrorKind::TimedOut.into()),
+        MailboxResult::DeserializationFailed(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+    }
+}
+
+fn wait_for_receiver(tag: Tag, timeout: Duration) -> io::Result<Vec<u8>> {
+    unwrap_raw(receive_with(&[tag], Some(timeout), read_raw))
+}
+
+/// Wait for the receiver to acknowledge a chunk, returns the credit gained.
+fn take_ack(tag: Tag, timeout: Duration) -> io::Result<usize> {
+    match wait_for_receiver(tag, timeout)?.as_slice() {
+        [ACK] => Ok(1),
+        [CANCEL] => Err(io::Error::new(io::ErrorKind::BrokenPipe, "receiver dropped the stream")),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected stream message")),
+    }
+}
+
+/// Read from `reader` until `buffer` is full or the reader is exhausted.
+fn fill(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(len)
+}
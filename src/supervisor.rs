@@ -0,0 +1,242 @@
+//! Supervisors restart linked children that die.
+//!
+//! A supervisor is a process that starts the children declared in
+//! [`Supervisor::init`], links to them and restarts them according to its
+//! [`Strategy`] when they die. A supervisor can itself be the child of another
+//! supervisor, which is how supervision trees are built: if a supervisor gives
+//! up because its children restart too often, it dies and its own supervisor
+//! decides what to do.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use hyperwasm::{supervisor::{ChildSpec, Strategy, Supervisor, SupervisorConfig}, Mailbox};
+//!
+//! struct Workers;
+//!
+//! impl Supervisor for Workers {
+//!     type Arg = u32;
+//!
+//!     fn init(config: &mut SupervisorConfig, count: u32) {
+//!         config.set_strategy(Strategy::OneForOne);
+//!         config.set_restart_intensity(5, Duration::from_secs(10));
+//!         for i in 0..count {
+//!             config.add_child(ChildSpec::new(&format!("worker {}", i), i, |i, _: Mailbox<()>| {
+//!                 println!("worker {} running", i);
+//!             }));
+//!         }
+//!     }
+//! }
+//!
+//! Workers::start(4);
+//! ```
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    serializer::{Bincode, Serializer},
+    tag::Tag,
+    Mailbox, MailboxResult, Process, ProcessConfig,
+};
+
+/// Which children are restarted when one of them dies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// Only the child that died.
+    OneForOne,
+    /// All children.
+    OneForAll,
+    /// The child that died and all children declared after it.
+    RestForOne,
+}
+
+/// A supervisor, declaring its children and how to restart them.
+pub trait Supervisor {
+    /// Argument passed to [`init`](Supervisor::init) in the supervisor process.
+    type Arg;
+
+    fn init(config: &mut SupervisorConfig, arg: Self::Arg);
+
+    /// Spawn the supervisor process.
+    fn start(arg: Self::Arg) -> Process<()>
+    where
+        Self: Sized + 'static,
+        Self::Arg: Serialize + DeserializeOwned,
+    {
+        Process::spawn(arg, supervisor_entry::<Self>)
+    }
+}
+
+/// Settings filled in by [`Supervisor::init`].
+///
+/// Defaults to [`Strategy::OneForOne`] with at most 3 restarts in 5 seconds.
+pub struct SupervisorConfig {
+    strategy: Strategy,
+    max_restarts: usize,
+    period: Duration,
+    children: Vec<ChildSpec>,
+}
+
+impl SupervisorConfig {
+    fn new() -> Self {
+        Self {
+            strategy: Strategy::OneForOne,
+            max_restarts: 3,
+            period: Duration::from_secs(5),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        self.strategy = strategy;
+    }
+
+    /// Give up once more than `max_restarts` restarts happen within `period`.
+    ///
+    /// Giving up kills all children and then the supervisor itself.
+    pub fn set_restart_intensity(&mut self, max_restarts: usize, period: Duration) {
+        self.max_restarts = max_restarts;
+        self.period = period;
+    }
+
+    /// Declare a child. Children are started in the order they are added.
+    pub fn add_child(&mut self, child: ChildSpec) {
+        self.children.push(child);
+    }
+}
+
+type StartFn = Box<dyn Fn(Option<&ProcessConfig>) -> (Process<()>, Tag)>;
+
+/// How to start a supervised child.
+pub struct ChildSpec {
+    name: String,
+    config: Option<ProcessConfig>,
+    start: StartFn,
+}
+
+impl ChildSpec {
+    /// A child running `entry` with a copy of `capture` on every (re)start.
+    pub fn new<C, M, S>(name: &str, capture: C, entry: fn(C, Mailbox<M, S>)) -> Self
+    where
+        C: Clone + 'static,
+        M: 'static,
+        S: Serializer<C> + Serializer<M> + 'static,
+    {
+        let start = move |config: Option<&ProcessConfig>| {
+            let (child, tag) = match config {
+                Some(config) => Process::<M, S>::spawn_link_config(config, capture.clone(), entry),
+                None => Process::<M, S>::spawn_link(capture.clone(), entry),
+            };
+            (Process::new(child.node_id(), child.id()), tag)
+        };
+        Self {
+            name: name.to_owned(),
+            config: None,
+            start: Box::new(start),
+        }
+    }
+
+    /// A nested supervisor of type `T`.
+    pub fn supervisor<T>(name: &str, arg: T::Arg) -> Self
+    where
+        T: Supervisor + 'static,
+        T::Arg: Clone + Serialize + DeserializeOwned + 'static,
+    {
+        Self::new::<T::Arg, (), Bincode>(name, arg, supervisor_entry::<T>)
+    }
+
+    /// Start the child with `config` every time, keeping its scheduling
+    /// parameters and limits across restarts.
+    pub fn config(mut self, config: ProcessConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn start(&self) -> (Process<()>, Tag) {
+        (self.start)(self.config.as_ref())
+    }
+}
+
+struct RunningChild {
+    spec: ChildSpec,
+    process: Process<()>,
+    tag: Tag,
+}
+
+impl RunningChild {
+    fn restart(&mut self) {
+        let (process, tag) = self.spec.start();
+        self.process = process;
+        self.tag = tag;
+    }
+
+    fn stop(&self) {
+        self.process.unlink();
+        self.process.kill();
+    }
+}
+
+fn supervisor_entry<T: Supervisor>(arg: T::Arg, mailbox: Mailbox<()>) {
+    let mut config = SupervisorConfig::new();
+    T::init(&mut config, arg);
+    let mailbox = mailbox.catch_link_failure();
+
+    let mut children: Vec<RunningChild> = config
+        .children
+        .into_iter()
+        .map(|spec| {
+            let (process, tag) = spec.start();
+            RunningChild { spec, process, tag }
+        })
+        .collect();
+    let mut restarts = VecDeque::new();
+
+    loop {
+        let tag = match mailbox.receive() {
+            MailboxResult::LinkDied(tag) => tag,
+            _ => continue,
+        };
+        // Children stopped by a previous restart report with a stale tag.
+        let Some(index) = children.iter().position(|child| child.tag == tag) else {
+            continue;
+        };
+
+        let now = Instant::now();
+        restarts.push_back(now);
+        while restarts
+            .front()
+            .is_some_and(|&restart| now.duration_since(restart) > config.period)
+        {
+            restarts.pop_front();
+        }
+        if restarts.len() > config.max_restarts {
+            children.iter().for_each(RunningChild::stop);
+            panic!(
+                "Supervisor {} gave up after {} restarts of {:?} within {:?}",
+                std::any::type_name::<T>(),
+                restarts.len(),
+                children[index].spec.name,
+                config.period
+            );
+        }
+
+        let restart = match config.strategy {
+            Strategy::OneForOne => index..index + 1,
+            Strategy::OneForAll => 0..children.len(),
+            Strategy::RestForOne => index..children.len(),
+        };
+        for (i, child) in children[restart.clone()].iter().enumerate() {
+            if restart.start + i != index {
+                child.stop();
+            }
+        }
+        children[restart].iter_mut().for_each(RunningChild::restart);
+    }
+}
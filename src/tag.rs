@@ -1,8 +1,14 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct Tag(i64);
 
 impl Tag {
+    // Ids below the special range are reserved for tags used by the library.
+    /// Carried by the header of every stream, see [`crate::stream`].
+    pub(crate) const STREAM_HEADER: Tag = Tag(1);
+
     /// Create tag of any value.
     pub(crate) fn from(id: i64) -> Tag {
         Tag(id)
@@ -10,10 +16,7 @@ impl Tag {
 
 
     pub fn new() -> Tag {
-        unsafe {
-            COUNTER += 1;
-            Tag(COUNTER)
-        }
+        Tag(COUNTER.fetch_add(1, Ordering::Relaxed) + 1)
     }
 
     pub fn none() -> Tag {
@@ -35,7 +38,7 @@ impl Tag {
 }
 
 
-static mut COUNTER: i64 = 128;
+static COUNTER: AtomicI64 = AtomicI64::new(128);
 
 impl Tag {}
 
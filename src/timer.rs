@@ -0,0 +1,74 @@
+//! Timers backed by the hwe::timer host functions.
+use std::time::{Duration, Instant};
+
+use crate::host::api::{process, timer};
+
+/// A message that will be delivered in the future.
+///
+/// Returned by [`Process::send_after`](crate::Process::send_after).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerRef(u64);
+
+impl TimerRef {
+    pub(crate) fn from(id: u64) -> Self {
+        TimerRef(id)
+    }
+
+    /// Cancels the timer.
+    ///
+    /// Returns `false` if the message was already delivered or the timer was
+    /// cancelled before.
+    pub fn cancel(self) -> bool {
+        unsafe { timer::cancel_timer(self.0) == 1 }
+    }
+}
+
+/// Suspends the current process for `duration`.
+pub fn sleep(duration: Duration) {
+    crate::version::assert_supported();
+    unsafe { process::sleep_ms(duration.as_millis() as u64) };
+}
+
+/// Releases the current process at a fixed rate.
+///
+/// Each [`tick`](Interval::tick) sleeps until the next period boundary and
+/// re-arms the interval for the one after it, so time spent between ticks
+/// does not make the period drift. Boundaries that already passed are skipped.
+#[derive(Debug, Clone)]
+pub struct Interval {
+    period: Duration,
+    next: Instant,
+}
+
+impl Interval {
+    /// Creates an interval whose first tick completes immediately.
+    pub fn new(period: Duration) -> Self {
+        assert!(!period.is_zero(), "interval period must be non-zero");
+        Self {
+            period,
+            next: Instant::now(),
+        }
+    }
+
+    /// Waits for the next tick.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if self.next > now {
+            sleep(self.next - now);
+        }
+        self.next += self.period;
+        let now = Instant::now();
+        while self.next <= now {
+            self.next += self.period;
+        }
+    }
+
+    /// Time left until the next tick, useful as a receive timeout.
+    pub fn remaining(&self) -> Duration {
+        self.next.saturating_duration_since(Instant::now())
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
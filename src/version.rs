@@ -0,0 +1,108 @@
+//! Version of the hwe host ABI and the APIs it provides.
+use std::{fmt, sync::OnceLock};
+
+use crate::{error::HperwasmError, host::api::version};
+
+/// A `major.minor.patch` version of the host ABI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The oldest host ABI this library works with.
+pub const MIN_HOST_VERSION: Version = Version::new(0, 1, 0);
+
+/// The newest host ABI this library knows about.
+pub const LATEST_HOST_VERSION: Version = Version::new(0, 2, 0);
+
+static HOST_VERSION: OnceLock<Version> = OnceLock::new();
+
+/// Version of the host ABI, queried once and cached.
+pub fn host_version() -> Version {
+    *HOST_VERSION.get_or_init(|| unsafe {
+        Version::new(version::major(), version::minor(), version::patch())
+    })
+}
+
+/// Check that the host is at least [`MIN_HOST_VERSION`].
+///
+/// Every entry into the host runs this check first: spawning, sending,
+/// receiving, sleeping, creating configs or modules and opening sockets. An
+/// old host fails with [`HperwasmError::UnsupportedHost`] instead of a trap
+/// in some later host call.
+pub fn ensure_supported() -> Result<(), HperwasmError> {
+    let host = host_version();
+    if host >= MIN_HOST_VERSION {
+        Ok(())
+    } else {
+        Err(HperwasmError::UnsupportedHost {
+            host,
+            required: MIN_HOST_VERSION,
+        })
+    }
+}
+
+/// Panic with [`HperwasmError::UnsupportedHost`] if the host is too old, for
+/// entry points that can't return an error.
+#[track_caller]
+pub(crate) fn assert_supported() {
+    if let Err(err) = ensure_supported() {
+        panic!("{}", err);
+    }
+}
+
+/// Groups of host functions, matching the `hwe::*` import modules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Api {
+    Error,
+    Message,
+    Timer,
+    Networking,
+    Process,
+    Registry,
+    Wasi,
+    Distributed,
+}
+
+impl Api {
+    /// The first host version providing this API.
+    pub fn min_version(self) -> Version {
+        match self {
+            Api::Error
+            | Api::Message
+            | Api::Timer
+            | Api::Networking
+            | Api::Process
+            | Api::Registry
+            | Api::Wasi => MIN_HOST_VERSION,
+            Api::Distributed => Version::new(0, 2, 0),
+        }
+    }
+
+    /// Whether the running host provides this API and this build can use
+    /// it, [`Api::Distributed`] also needs the `distributed` feature.
+    pub fn is_available(self) -> bool {
+        if self == Api::Distributed && !cfg!(feature = "distributed") {
+            return false;
+        }
+        host_version() >= self.min_version()
+    }
+}
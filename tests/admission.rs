@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use hyperwasm::{
+    admission::{is_schedulable, liu_layland_bound, Policy},
+    InvalidSchedulingParams, SchedulingParams,
+};
+
+fn task(wcet: u64, period: u64) -> SchedulingParams {
+    SchedulingParams::implicit(Duration::from_millis(wcet), Duration::from_millis(period)).unwrap()
+}
+
+fn constrained(wcet: u64, period: u64, deadline: u64) -> SchedulingParams {
+    SchedulingParams::new(
+        Duration::from_millis(wcet),
+        Duration::from_millis(period),
+        Duration::from_millis(deadline),
+    )
+    .unwrap()
+}
+
+#[test]
+fn params_are_validated() {
+    let ms = Duration::from_millis;
+    assert_eq!(
+        SchedulingParams::new(ms(0), ms(10), ms(10)),
+        Err(InvalidSchedulingParams::ZeroExecutionTime)
+    );
+    assert!(matches!(
+        SchedulingParams::new(ms(5), ms(10), ms(4)),
+        Err(InvalidSchedulingParams::ExecutionTimeExceedsDeadline { .. })
+    ));
+    assert!(matches!(
+        SchedulingParams::new(ms(5), ms(10), ms(11)),
+        Err(InvalidSchedulingParams::DeadlineExceedsPeriod { .. })
+    ));
+}
+
+#[test]
+fn edf_accepts_full_utilization() {
+    assert!(is_schedulable(Policy::Edf, &[task(1, 2), task(1, 2)]));
+    assert!(is_schedulable(
+        Policy::Edf,
+        &[task(1, 3), task(1, 3), task(1, 3)]
+    ));
+    assert!(is_schedulable(Policy::Edf, &[task(2, 5), task(3, 5)]));
+}
+
+#[test]
+fn edf_rejects_overload() {
+    assert!(!is_schedulable(
+        Policy::Edf,
+        &[task(1, 2), task(1, 2), task(1, 100)]
+    ));
+}
+
+#[test]
+fn edf_uses_density_for_constrained_deadlines() {
+    // Utilization 0.6, but density 0.5 + 0.5 + 0.1.
+    assert!(!is_schedulable(
+        Policy::Edf,
+        &[constrained(1, 4, 2), constrained(1, 4, 2), task(1, 10)]
+    ));
+}
+
+#[test]
+fn liu_layland_bounds() {
+    assert!((liu_layland_bound(1) - 1.0).abs() < 1e-12);
+    assert!((liu_layland_bound(2) - 0.828_427).abs() < 1e-6);
+    assert!((liu_layland_bound(3) - 0.779_763).abs() < 1e-6);
+}
+
+#[test]
+fn rate_monotonic_below_bound() {
+    // n = 2, U = 0.8 < 0.828.
+    assert!(is_schedulable(
+        Policy::RateMonotonic,
+        &[task(2, 5), task(4, 10)]
+    ));
+    // n = 3, U = 0.75 < 0.780.
+    assert!(is_schedulable(
+        Policy::RateMonotonic,
+        &[task(1, 4), task(2, 8), task(4, 16)]
+    ));
+}
+
+#[test]
+fn rate_monotonic_harmonic_passes_response_time_analysis() {
+    // U = 1.0 fails Liu & Layland, the harmonic periods still fit exactly.
+    assert!(is_schedulable(
+        Policy::RateMonotonic,
+        &[task(1, 2), task(2, 4)]
+    ));
+}
+
+#[test]
+fn rate_monotonic_rejects_what_edf_accepts() {
+    // U = 1.0 without harmonic periods: the second task responds after 7 > 6.
+    let tasks = [task(2, 4), task(3, 6)];
+    assert!(!is_schedulable(Policy::RateMonotonic, &tasks));
+    assert!(is_schedulable(Policy::Edf, &tasks));
+}
+
+#[test]
+fn rate_monotonic_above_bound_but_schedulable() {
+    // U = 0.9 > 0.828, response time of the second task is 5 + 2 * 2 = 9 <= 10.
+    assert!(is_schedulable(
+        Policy::RateMonotonic,
+        &[task(2, 5), task(5, 10)]
+    ));
+}
+
+#[test]
+fn response_time_analysis_survives_huge_periods() {
+    let huge = SchedulingParams::implicit(Duration::MAX / 2, Duration::MAX).unwrap();
+    assert!(!is_schedulable(
+        Policy::RateMonotonic,
+        &[task(1, 2), huge, huge]
+    ));
+}
+
+#[cfg(feature = "mock_host")]
+mod spawn {
+    use hyperwasm::{
+        admission::{AdmissionController, AdmissionError, Enforcement},
+        periodic::PeriodicTask,
+        Mailbox, ProcessConfig,
+    };
+
+    use super::*;
+
+    fn config(params: SchedulingParams) -> ProcessConfig {
+        let mut config = ProcessConfig::new().unwrap();
+        config.set_scheduling(params);
+        config
+    }
+
+    #[test]
+    fn spawn_tracks_until_release() {
+        let mut controller = AdmissionController::new(Policy::Edf);
+        let (first, overload) = controller
+            .spawn(&config(task(6, 10)), (), |(), _: Mailbox<()>| {})
+            .unwrap();
+        assert!(overload.is_none());
+        assert_eq!(controller.len(), 1);
+        assert!((controller.utilization() - 0.6).abs() < 1e-9);
+
+        let second = controller.spawn(&config(task(6, 10)), (), |(), _: Mailbox<()>| {});
+        match second {
+            Err(AdmissionError::Unschedulable(overload)) => {
+                assert_eq!(overload.policy, Policy::Edf);
+                assert!((overload.added - 0.6).abs() < 1e-9);
+                assert!((overload.utilization - 1.2).abs() < 1e-9);
+            }
+            _ => panic!("expected the second process to be rejected"),
+        }
+        assert_eq!(controller.len(), 1);
+
+        controller.release(first.id());
+        assert!(controller.is_empty());
+        assert!(controller
+            .spawn(&config(task(6, 10)), (), |(), _: Mailbox<()>| {})
+            .is_ok());
+    }
+
+    #[test]
+    fn warn_spawns_and_reports_overload() {
+        let mut controller = AdmissionController::new(Policy::Edf).enforcement(Enforcement::Warn);
+        controller
+            .spawn(&config(task(6, 10)), (), |(), _: Mailbox<()>| {})
+            .unwrap();
+        let (_, overload) = controller
+            .spawn(&config(task(6, 10)), (), |(), _: Mailbox<()>| {})
+            .unwrap();
+        assert!((overload.unwrap().utilization - 1.2).abs() < 1e-9);
+        assert_eq!(controller.len(), 2);
+    }
+
+    #[test]
+    fn spawn_needs_scheduling_params() {
+        let mut controller = AdmissionController::new(Policy::Edf);
+        let mut config = config(task(6, 10));
+        // Raw times can't be checked, they drop the parameters.
+        config.set_expected_time(7);
+        assert_eq!(config.scheduling(), None);
+        assert!(matches!(
+            controller.spawn(&config, (), |(), _: Mailbox<()>| {}),
+            Err(AdmissionError::MissingParams)
+        ));
+    }
+
+    #[test]
+    fn periodic_tasks_are_admitted() {
+        let mut controller = AdmissionController::new(Policy::RateMonotonic);
+        let (first, _): (PeriodicTask, _) =
+            controller.spawn_periodic(task(5, 10), (), |()| {}).unwrap();
+        let (second, _) = controller.spawn_periodic(task(5, 10), (), |()| {}).unwrap();
+        assert!(matches!(
+            controller.spawn_periodic(task(1, 10), (), |()| {}),
+            Err(AdmissionError::Unschedulable(_))
+        ));
+        controller.release_periodic(first);
+        assert_eq!(controller.len(), 1);
+        controller.release_periodic(second);
+        assert!(controller.is_empty());
+    }
+}
@@ -0,0 +1,133 @@
+#![cfg(all(feature = "mock_host", feature = "compression"))]
+
+use std::time::Duration;
+
+use hyperwasm::{
+    serializer::{
+        AnyDecoder, Bincode, Compressed, DecodeError, Deflate, Lz4, Schema, Serializer, Versioned,
+    },
+    Mailbox, MailboxResult, Process,
+};
+use serde::{Deserialize, Serialize};
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Send `message` to the calling process with `S` and receive it back as `R`.
+fn round_trip<M, R, S>(message: M) -> MailboxResult<R>
+where
+    S: Serializer<M> + Serializer<R>,
+{
+    Process::<M, S>::this().send(message);
+    unsafe { Mailbox::<R, S>::new() }.receive_timeout(TIMEOUT)
+}
+
+fn compressible(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i / 100) as u8).collect()
+}
+
+/// Send `message` with `S` and return the raw message as it arrived.
+fn raw<M, S: Serializer<M>>(message: M) -> Vec<u8> {
+    Process::<M, S>::this().send(message);
+    unsafe { Mailbox::<()>::new() }.receive_raw()
+}
+
+#[test]
+fn lz4_round_trip() {
+    let data = compressible(64 * 1024);
+    let received = round_trip::<_, Vec<u8>, Compressed>(data.clone());
+    assert_eq!(received.unwrap(), data);
+}
+
+#[test]
+fn deflate_round_trip() {
+    let data = compressible(64 * 1024);
+    let received = round_trip::<_, Vec<u8>, Compressed<Bincode, Deflate>>(data.clone());
+    assert_eq!(received.unwrap(), data);
+}
+
+#[test]
+fn large_messages_are_compressed() {
+    let data = compressible(64 * 1024);
+    let message = raw::<_, Compressed<Bincode, Lz4>>(data.clone());
+    assert_ne!(message[0], 0);
+    assert!(message.len() < data.len() / 4);
+}
+
+#[test]
+fn small_messages_are_not_compressed() {
+    let data = vec![1u8, 2, 3];
+    let message = raw::<_, Compressed>(data.clone());
+    assert_eq!(message[0], 0);
+    assert_eq!(
+        round_trip::<_, Vec<u8>, Compressed>(data.clone()).unwrap(),
+        data
+    );
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct FrameV1 {
+    label: String,
+}
+
+impl Schema for FrameV1 {
+    const NAME: &'static str = "tests::Frame";
+    const VERSION: u32 = 1;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Frame {
+    id: u32,
+    label: String,
+}
+
+impl Schema for Frame {
+    const NAME: &'static str = "tests::Frame";
+    const VERSION: u32 = 2;
+
+    fn upgrade<S: AnyDecoder>(version: u32) -> Option<Result<Self, DecodeError>> {
+        match version {
+            1 => Some(S::decode_any::<FrameV1>().map(|old| Frame {
+                id: 0,
+                label: old.label,
+            })),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn versioned_compressed() {
+    let frame = Frame {
+        id: 9,
+        label: "x".repeat(4096),
+    };
+    let received = round_trip::<_, Frame, Versioned<Compressed>>(frame.clone());
+    assert_eq!(received.unwrap(), frame);
+}
+
+#[cfg(feature = "json_serializer")]
+#[test]
+fn json_round_trip() {
+    use hyperwasm::serializer::Json;
+
+    let data = compressible(16 * 1024);
+    let received = round_trip::<_, Vec<u8>, Compressed<Json>>(data.clone());
+    assert_eq!(received.unwrap(), data);
+}
+
+#[test]
+fn versioned_compressed_upgrade() {
+    // Large enough to be compressed, the upgrade has to decompress it.
+    let old = FrameV1 {
+        label: "y".repeat(4096),
+    };
+    assert_ne!(raw::<_, Versioned<Compressed>>(old.clone())[12], 0);
+    let received = round_trip::<_, Frame, Versioned<Compressed>>(old);
+    assert_eq!(
+        received.unwrap(),
+        Frame {
+            id: 0,
+            label: "y".repeat(4096)
+        }
+    );
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "mock_host")]
+
+use std::time::{Duration, Instant};
+
+use hyperwasm::{sleep, Mailbox, MailboxResult, Process, Tag};
+
+#[test]
+fn tagged_receive_skips_other_messages() {
+    let this = Process::<u32>::this();
+    let tag = Tag::new();
+    this.send(1);
+    this.tag_send(tag, 2);
+    let mailbox = unsafe { Mailbox::<u32>::new() };
+    assert_eq!(mailbox.tag_receive(&[tag]), 2);
+    assert_eq!(mailbox.receive(), 1);
+}
+
+#[test]
+fn tagged_receive_times_out() {
+    let mailbox = unsafe { Mailbox::<u32>::new() };
+    Process::<u32>::this().send(1);
+    assert!(mailbox
+        .tag_receive_timeout(&[Tag::new()], Duration::from_millis(20))
+        .is_timed_out());
+    assert_eq!(mailbox.receive(), 1);
+}
+
+#[test]
+fn link_failure_is_caught() {
+    let mailbox = unsafe { Mailbox::<()>::new() }.catch_link_failure();
+    let (_, tag) = Process::<()>::spawn_link((), |(), _: Mailbox<()>| panic!("child failed"));
+    match mailbox.receive() {
+        MailboxResult::LinkDied(died) => assert_eq!(died, tag),
+        _ => panic!("expected the link to die"),
+    }
+}
+
+#[test]
+fn normal_exit_does_not_notify_links() {
+    let mailbox = unsafe { Mailbox::<()>::new() }.catch_link_failure();
+    Process::<()>::spawn_link((), |(), _: Mailbox<()>| {});
+    assert!(mailbox
+        .try_receive(Duration::from_millis(50))
+        .is_timed_out());
+}
+
+#[test]
+fn registry() {
+    let name = "mock registry test";
+    let this = Process::<u32>::this();
+    this.register(name);
+    assert_eq!(Process::<u32>::lookup(name), Some(this));
+    // Registrations are per message type.
+    assert_eq!(Process::<u64>::lookup(name), None);
+    assert!(Process::<u32>::send_to_name(name, 7));
+    assert_eq!(unsafe { Mailbox::<u32>::new() }.receive(), 7);
+
+    Process::<u32>::unregister(name);
+    assert_eq!(Process::<u32>::lookup(name), None);
+    assert!(!Process::<u32>::send_to_name(name, 8));
+}
+
+#[test]
+fn timers() {
+    let this = Process::<u32>::this();
+    let mailbox = unsafe { Mailbox::<u32>::new() };
+    let start = Instant::now();
+    this.send_after(1, Duration::from_millis(30));
+    assert_eq!(mailbox.receive(), 1);
+    assert!(start.elapsed() >= Duration::from_millis(30));
+
+    let timer = this.send_after(2, Duration::from_millis(30));
+    assert!(timer.cancel());
+    assert!(mailbox
+        .receive_timeout(Duration::from_millis(60))
+        .is_timed_out());
+}
+
+#[test]
+fn sleep_blocks() {
+    let start = Instant::now();
+    sleep(Duration::from_millis(20));
+    assert!(start.elapsed() >= Duration::from_millis(20));
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "mock_host")]
+
+use std::time::Duration;
+
+use hyperwasm::{
+    periodic::JobReport, spawn_periodic, Mailbox, MailboxResult, Process, SchedulingParams,
+};
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn jobs_are_released_every_period() {
+    let params = SchedulingParams::implicit(ms(5), ms(20)).unwrap();
+    let task = spawn_periodic(params, Process::<u64>::this(), |parent| parent.send(1));
+    let mailbox = unsafe { Mailbox::<u64>::new() };
+    let mut jobs = 0;
+    while let MailboxResult::Message(_) = mailbox.receive_timeout(ms(100)) {
+        jobs += 1;
+        if jobs == 3 {
+            break;
+        }
+    }
+    assert_eq!(jobs, 3);
+    assert_eq!(task.next_report(ms(0)), None);
+}
+
+#[test]
+fn missed_deadlines_are_reported() {
+    let params = SchedulingParams::new(ms(5), ms(50), ms(10)).unwrap();
+    let task = spawn_periodic(params, (), |()| {
+        std::thread::sleep(Duration::from_millis(30))
+    });
+    assert_eq!(
+        task.next_report(ms(500)),
+        Some(JobReport::Missed { job: 0 })
+    );
+}
+
+#[test]
+fn dropping_the_handle_stops_the_task() {
+    let params = SchedulingParams::implicit(ms(5), ms(20)).unwrap();
+    let task = spawn_periodic(params, Process::<u64>::this(), |parent| parent.send(1));
+    let mailbox = unsafe { Mailbox::<u64>::new() };
+    assert!(mailbox.receive_timeout(ms(100)).is_message());
+    drop(task);
+
+    // Let a job released before the stop finish, then nothing follows.
+    std::thread::sleep(ms(50));
+    while mailbox.receive_timeout(ms(0)).is_message() {}
+    assert!(mailbox.receive_timeout(ms(100)).is_timed_out());
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "mock_host")]
+
+use std::time::Duration;
+
+use hyperwasm::{
+    serializer::{Bincode, Serializer},
+    spawn, Mailbox, MailboxResult, Process,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn next<M>(mailbox: &Mailbox<M>) -> M
+where
+    Bincode: Serializer<M>,
+{
+    match mailbox.receive_timeout(TIMEOUT) {
+        MailboxResult::Message(message) => message,
+        _ => panic!("no message arrived"),
+    }
+}
+
+#[test]
+fn capture_is_transferred() {
+    let parent = Process::<(String, Vec<u32>)>::this();
+    Process::<()>::spawn(
+        (parent, "captured".to_owned(), vec![1, 2, 3]),
+        |(parent, text, numbers), _: Mailbox<()>| parent.send((text, numbers)),
+    );
+    let mailbox = unsafe { Mailbox::<(String, Vec<u32>)>::new() };
+    assert_eq!(next(&mailbox), ("captured".to_owned(), vec![1, 2, 3]));
+}
+
+#[test]
+fn spawn_macro_captures_variables() {
+    let parent = Process::<u64>::this();
+    let base = 40u64;
+    spawn!(|parent, base, offset = { 2u64 }| parent.send(base + offset));
+    let mailbox = unsafe { Mailbox::<u64>::new() };
+    assert_eq!(next(&mailbox), 42);
+}
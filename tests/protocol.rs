@@ -0,0 +1,52 @@
+#![cfg(feature = "mock_host")]
+
+use hyperwasm::{
+    protocol::{Branch, End, Offer, Protocol, Recv, Send},
+    spawn_link,
+};
+
+#[test]
+fn send_and_receive() {
+    type Length = Recv<String, Send<usize, End>>;
+
+    let (child, _tag) = spawn_link!(|protocol: Protocol<Length>| {
+        let (protocol, text) = protocol.receive();
+        protocol.send(text.len()).close();
+    });
+    let (child, len) = child.send("hello".to_owned()).receive();
+    child.close();
+    assert_eq!(len, 5);
+}
+
+#[test]
+fn offer_and_choose() {
+    type Calculator = Offer<Recv<i32, Send<i32, End>>, Recv<(i32, i32), Send<i32, End>>>;
+
+    let spawn = || {
+        spawn_link!(|protocol: Protocol<Calculator>| match protocol.offer() {
+            Branch::Left(protocol) => {
+                let (protocol, n) = protocol.receive();
+                protocol.send(-n).close();
+            }
+            Branch::Right(protocol) => {
+                let (protocol, (a, b)) = protocol.receive();
+                protocol.send(a + b).close();
+            }
+        })
+        .0
+    };
+
+    let (negate, n): (Protocol<End>, _) = spawn().select_left().send(3).receive();
+    negate.close();
+    assert_eq!(n, -3);
+
+    let (add, sum) = spawn().select_right().send((2, 5)).receive();
+    add.close();
+    assert_eq!(sum, 7);
+}
+
+#[test]
+fn task_returns_its_result() {
+    let (task, _tag) = spawn_link!(@task |a = { 6u64 }, b = { 7u64 }| a * b);
+    assert_eq!(task.result(), 42);
+}
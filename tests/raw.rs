@@ -0,0 +1,29 @@
+#![cfg(feature = "mock_host")]
+
+use hyperwasm::{Mailbox, Process, Tag};
+
+#[test]
+fn raw_messages() {
+    let this = Process::<()>::this();
+    this.send_raw(b"raw bytes");
+    let mailbox = unsafe { Mailbox::<()>::new() };
+    assert_eq!(mailbox.receive_raw(), b"raw bytes");
+}
+
+#[test]
+fn tagged_raw_messages() {
+    let this = Process::<()>::this();
+    let tag = Tag::new();
+    this.send_raw(b"untagged");
+    this.send_raw_tagged(tag, b"tagged");
+    let mailbox = unsafe { Mailbox::<()>::new() };
+    assert_eq!(mailbox.tag_receive_raw(&[tag]), b"tagged");
+    assert_eq!(mailbox.receive_raw(), b"untagged");
+}
+
+#[test]
+fn large_message() {
+    let data: Vec<u8> = (0..1 << 20).map(|i| i as u8).collect();
+    Process::<Vec<u8>>::this().send(data.clone());
+    assert_eq!(unsafe { Mailbox::<Vec<u8>>::new() }.receive(), data);
+}
@@ -0,0 +1,41 @@
+#![cfg(feature = "mock_host")]
+
+use std::time::Duration;
+
+use hyperwasm::Process;
+
+/// A handle to process `id` on a node other than the current one.
+fn remote(id: u64) -> Process<u32> {
+    let node = hyperwasm::host::node_id() + 1;
+    bincode::deserialize(&bincode::serialize(&(node, id)).unwrap()).unwrap()
+}
+
+#[test]
+fn handles_know_their_node() {
+    assert!(Process::<u32>::this().is_local());
+    assert!(!remote(1).is_local());
+}
+
+#[test]
+#[should_panic(expected = "only processes on this node")]
+fn remote_kill_panics() {
+    remote(Process::<u32>::this().id()).kill();
+}
+
+#[test]
+#[should_panic(expected = "only processes on this node")]
+fn remote_link_panics() {
+    remote(Process::<u32>::this().id()).link();
+}
+
+#[test]
+#[should_panic(expected = "only processes on this node")]
+fn remote_register_panics() {
+    remote(1).register("remote");
+}
+
+#[test]
+#[should_panic(expected = "only processes on this node")]
+fn remote_timer_panics() {
+    remote(1).send_after(1, Duration::from_millis(10));
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "mock_host")]
+
+use std::time::Duration;
+
+use hyperwasm::{Mailbox, Process, Request, RequestError};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn request_reply() {
+    let doubler = Process::spawn((), |(), mailbox: Mailbox<Request<u32, u32>>| loop {
+        mailbox.answer(|n| n * 2);
+    });
+    assert_eq!(doubler.request(21, TIMEOUT).unwrap(), 42);
+    assert_eq!(doubler.request(5, TIMEOUT).unwrap(), 10);
+}
+
+#[test]
+fn request_times_out() {
+    let silent = Process::spawn((), |(), mailbox: Mailbox<Request<u32, u32>>| {
+        let _ = mailbox.receive();
+        std::thread::sleep(Duration::from_secs(1));
+    });
+    assert!(matches!(
+        silent.request(1, Duration::from_millis(50)),
+        Err(RequestError::Timeout)
+    ));
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "mock_host")]
+
+use std::time::Duration;
+
+use hyperwasm::{serializer::Bincode, server::Server, RequestError};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Counter;
+
+impl Server for Counter {
+    type Arg = u64;
+    type State = u64;
+    type Call = ();
+    type Response = u64;
+    type Cast = u64;
+    type Serializer = Bincode;
+
+    fn init(start: u64) -> u64 {
+        start
+    }
+
+    fn handle_call(count: &mut u64, _: ()) -> u64 {
+        *count
+    }
+
+    fn handle_cast(count: &mut u64, add: u64) {
+        *count += add;
+    }
+}
+
+#[test]
+fn call_and_cast() {
+    let counter = Counter::start(10);
+    assert_eq!(counter.call((), TIMEOUT).unwrap(), 10);
+    counter.cast(5);
+    counter.cast(7);
+    assert_eq!(counter.call((), TIMEOUT).unwrap(), 22);
+    counter.shutdown();
+}
+
+#[test]
+fn call_after_shutdown_times_out() {
+    let counter = Counter::start(0);
+    counter.shutdown();
+    assert!(matches!(
+        counter.call((), Duration::from_millis(50)),
+        Err(RequestError::Timeout)
+    ));
+}
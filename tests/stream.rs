@@ -0,0 +1,118 @@
+#![cfg(feature = "mock_host")]
+
+use std::{io::Read, time::Duration};
+
+use hyperwasm::{stream::CHUNK_SIZE, Mailbox, MailboxResult, Process};
+
+type Outcome = Result<u64, String>;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn payload(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+/// Spawn a process streaming `data` to the caller, it reports the result
+/// of the send back afterwards.
+fn stream_to_this(data: Vec<u8>) -> Mailbox<Outcome> {
+    Process::<()>::spawn(
+        (Process::<Outcome>::this(), data),
+        |(parent, data), _: Mailbox<()>| {
+            let outcome = parent
+                .send_stream(&data[..], TIMEOUT)
+                .map_err(|err| format!("{:?}", err.kind()));
+            parent.send(outcome);
+        },
+    );
+    unsafe { Mailbox::new() }
+}
+
+fn outcome(mailbox: &Mailbox<Outcome>) -> Outcome {
+    match mailbox.receive_timeout(TIMEOUT) {
+        MailboxResult::Message(outcome) => outcome,
+        _ => panic!("sender did not report back"),
+    }
+}
+
+#[test]
+fn read_to_end() {
+    let data = payload(5 * CHUNK_SIZE + 17);
+    let mailbox = stream_to_this(data.clone());
+    let mut received = Vec::new();
+    mailbox
+        .receive_stream()
+        .unwrap()
+        .read_to_end(&mut received)
+        .unwrap();
+    assert_eq!(received, data);
+    assert_eq!(outcome(&mailbox), Ok(data.len() as u64));
+}
+
+#[test]
+fn empty_stream() {
+    let mailbox = stream_to_this(Vec::new());
+    let mut received = Vec::new();
+    mailbox
+        .receive_stream()
+        .unwrap()
+        .read_to_end(&mut received)
+        .unwrap();
+    assert!(received.is_empty());
+    assert_eq!(outcome(&mailbox), Ok(0));
+}
+
+#[test]
+fn deserialize_from_stream() {
+    // bincode stops at the last byte of the value and never sees the end
+    // of the stream.
+    let value = payload(2 * CHUNK_SIZE + 100);
+    let encoded = bincode::serialize(&value).unwrap();
+    let len = encoded.len() as u64;
+    let mailbox = stream_to_this(encoded);
+    let decoded: Vec<u8> = bincode::deserialize_from(mailbox.receive_stream().unwrap()).unwrap();
+    assert_eq!(decoded, value);
+    assert_eq!(outcome(&mailbox), Ok(len));
+    // Nothing of the stream is left in the mailbox.
+    assert!(matches!(
+        mailbox.receive_timeout(Duration::from_millis(50)),
+        MailboxResult::TimedOut
+    ));
+}
+
+#[test]
+fn dropping_reader_early_cancels_sender() {
+    let mailbox = stream_to_this(payload(20 * CHUNK_SIZE));
+    let mut reader = mailbox.receive_stream().unwrap();
+    let mut buf = [0; 100];
+    reader.read_exact(&mut buf).unwrap();
+    drop(reader);
+    assert_eq!(outcome(&mailbox), Err("BrokenPipe".to_string()));
+    assert!(matches!(
+        mailbox.receive_timeout(Duration::from_millis(50)),
+        MailboxResult::TimedOut
+    ));
+}
+
+#[test]
+fn sender_times_out_without_receiver() {
+    let receiver = Process::<()>::spawn((), |(), _: Mailbox<()>| {
+        std::thread::sleep(Duration::from_secs(1));
+    });
+    let result = receiver.send_stream(&payload(10)[..], Duration::from_millis(50));
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn other_messages_stay_queued() {
+    let mailbox = stream_to_this(payload(10));
+    Process::<Outcome>::this().send(Ok(7));
+    let mut received = Vec::new();
+    mailbox
+        .receive_stream()
+        .unwrap()
+        .read_to_end(&mut received)
+        .unwrap();
+    assert_eq!(received, payload(10));
+    assert_eq!(outcome(&mailbox), Ok(7));
+    assert_eq!(outcome(&mailbox), Ok(10));
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "mock_host")]
+
+use std::time::Duration;
+
+use hyperwasm::{
+    supervisor::{ChildSpec, Strategy, Supervisor, SupervisorConfig},
+    Mailbox, MailboxResult, Process,
+};
+
+const FAILING: u8 = 0;
+const STEADY: u8 = 1;
+
+/// Two children reporting to the test process every time they start, the
+/// first one fails shortly after. Restarts all children if the flag is set.
+struct Failing;
+
+impl Supervisor for Failing {
+    type Arg = (Process<u8>, bool);
+
+    fn init(config: &mut SupervisorConfig, (parent, one_for_all): Self::Arg) {
+        config.set_strategy(if one_for_all {
+            Strategy::OneForAll
+        } else {
+            Strategy::OneForOne
+        });
+        config.set_restart_intensity(2, Duration::from_secs(10));
+        config.add_child(ChildSpec::new(
+            "failing",
+            parent.clone(),
+            |parent, _: Mailbox<()>| {
+                parent.send(FAILING);
+                std::thread::sleep(Duration::from_millis(20));
+                panic!("failing child");
+            },
+        ));
+        config.add_child(ChildSpec::new(
+            "steady",
+            parent,
+            |parent, _: Mailbox<()>| {
+                parent.send(STEADY);
+                std::thread::sleep(Duration::from_secs(10));
+            },
+        ));
+    }
+}
+
+fn starts(one_for_all: bool) -> Vec<u8> {
+    Failing::start((Process::this(), one_for_all));
+    let mailbox = unsafe { Mailbox::<u8>::new() };
+    let mut starts = Vec::new();
+    while let MailboxResult::Message(child) = mailbox.receive_timeout(Duration::from_millis(500)) {
+        starts.push(child);
+    }
+    starts.sort();
+    starts
+}
+
+#[test]
+fn one_for_one_restarts_only_the_failed_child() {
+    // Started once and restarted twice before the supervisor gives up.
+    assert_eq!(starts(false), [FAILING, FAILING, FAILING, STEADY]);
+}
+
+#[test]
+fn one_for_all_restarts_every_child() {
+    assert_eq!(
+        starts(true),
+        [FAILING, FAILING, FAILING, STEADY, STEADY, STEADY]
+    );
+}
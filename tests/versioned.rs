@@ -0,0 +1,123 @@
+#![cfg(feature = "mock_host")]
+
+use std::time::Duration;
+
+use hyperwasm::{
+    serializer::{AnyDecoder, DecodeError, Schema, Serializer, Versioned},
+    Mailbox, MailboxResult, Process,
+};
+use serde::{Deserialize, Serialize};
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Send `message` to the calling process with `S` and receive it back as `R`.
+fn round_trip<M, R, S>(message: M) -> MailboxResult<R>
+where
+    S: Serializer<M> + Serializer<R>,
+{
+    Process::<M, S>::this().send(message);
+    unsafe { Mailbox::<R, S>::new() }.receive_timeout(TIMEOUT)
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct FrameV1 {
+    id: u32,
+}
+
+impl Schema for FrameV1 {
+    const NAME: &'static str = "tests::Frame";
+    const VERSION: u32 = 1;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Frame {
+    id: u32,
+    label: String,
+}
+
+impl From<FrameV1> for Frame {
+    fn from(old: FrameV1) -> Self {
+        Frame {
+            id: old.id,
+            label: String::new(),
+        }
+    }
+}
+
+impl Schema for Frame {
+    const NAME: &'static str = "tests::Frame";
+    const VERSION: u32 = 2;
+
+    fn upgrade<S: AnyDecoder>(version: u32) -> Option<Result<Self, DecodeError>> {
+        match version {
+            1 => Some(S::decode_any::<FrameV1>().map(Frame::from)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct FrameV3 {
+    id: u64,
+}
+
+impl Schema for FrameV3 {
+    const NAME: &'static str = "tests::Frame";
+    const VERSION: u32 = 3;
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Other {
+    id: u32,
+}
+
+impl Schema for Other {
+    const NAME: &'static str = "tests::Other";
+    const VERSION: u32 = 2;
+}
+
+#[test]
+fn versioned_round_trip() {
+    let frame = Frame {
+        id: 7,
+        label: "seven".to_owned(),
+    };
+    let received = round_trip::<_, Frame, Versioned>(frame.clone());
+    assert_eq!(received.unwrap(), frame);
+}
+
+#[test]
+fn versioned_rejects_other_type() {
+    match round_trip::<_, Frame, Versioned>(Other { id: 1 }) {
+        MailboxResult::DeserializationFailed(DecodeError::TypeMismatch { expected, .. }) => {
+            assert_eq!(expected, "tests::Frame")
+        }
+        _ => panic!("expected a type mismatch"),
+    }
+}
+
+#[test]
+fn versioned_upgrades_old_version() {
+    let received = round_trip::<_, Frame, Versioned>(FrameV1 { id: 3 });
+    assert_eq!(
+        received.unwrap(),
+        Frame {
+            id: 3,
+            label: String::new()
+        }
+    );
+}
+
+#[test]
+fn versioned_rejects_unknown_version() {
+    match round_trip::<_, Frame, Versioned>(FrameV3 { id: 3 }) {
+        MailboxResult::DeserializationFailed(DecodeError::VersionMismatch {
+            expected,
+            found,
+            ..
+        }) => {
+            assert_eq!((expected, found), (2, 3))
+        }
+        _ => panic!("expected a version mismatch"),
+    }
+}